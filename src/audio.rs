@@ -0,0 +1,150 @@
+use rodio::{Decoder, OutputStreamHandle, Sink, Source};
+use std::fs::File;
+use std::io::BufReader;
+
+/// Envolvente lineal: `value` avanza hacia `target` recorriendo [0, 1] en `seconds`.
+#[derive(Clone, Copy, Debug)]
+pub struct Fade {
+    pub value: f32,
+    pub target: f32,
+    seconds: f32,
+}
+
+impl Fade {
+    pub fn new(value: f32) -> Self {
+        Self { value, target: value, seconds: 0.0 }
+    }
+
+    /// Nuevo destino; con `seconds <= 0` el salto es inmediato.
+    pub fn set_target(&mut self, target: f32, seconds: f32) {
+        self.target = target;
+        self.seconds = seconds;
+        if seconds <= 0.0 { self.value = target; }
+    }
+
+    /// Avanza `dt` segundos y devuelve el valor actual.
+    pub fn update(&mut self, dt: f32) -> f32 {
+        if self.seconds <= 0.0 {
+            self.value = self.target;
+        } else {
+            let step = dt / self.seconds;
+            let diff = self.target - self.value;
+            self.value += diff.clamp(-step, step);
+        }
+        self.value
+    }
+
+    pub fn done(&self) -> bool {
+        self.value == self.target
+    }
+}
+
+/// Ganancia según la distancia al objetivo: 1 encima, `min_gain` a `range` o más (acotada).
+pub fn proximity_gain(dist: f32, range: f32, min_gain: f32) -> f32 {
+    let closeness = if range > 0.0 && dist.is_finite() { 1.0 - (dist / range).clamp(0.0, 1.0) } else { 0.0 };
+    min_gain + (1.0 - min_gain) * closeness
+}
+
+/// Música de fondo con fundido cruzado: como mucho dos sinks (el activo y el saliente),
+/// así que cambiar de pista nunca apila loops.
+pub struct Music {
+    handle: OutputStreamHandle,
+    base_volume: f32,
+    active: Option<(Sink, String)>,
+    /// Sink saliente y su volumen al empezar el fundido.
+    outgoing: Option<(Sink, f32)>,
+    /// 0 = sólo el saliente, 1 = sólo el activo.
+    mix: Fade,
+    /// Ganancia global (atenuación en victoria, intensidad...).
+    gain: Fade,
+}
+
+impl Music {
+    pub fn new(handle: &OutputStreamHandle, base_volume: f32) -> Self {
+        Self {
+            handle: handle.clone(),
+            base_volume,
+            active: None,
+            outgoing: None,
+            mix: Fade::new(1.0),
+            gain: Fade::new(1.0),
+        }
+    }
+
+    /// Pasa a `path` en bucle con un fundido cruzado de `seconds`.
+    /// Si ya suena esa pista no hace nada; si no se puede abrir, se mantiene la actual.
+    pub fn play(&mut self, path: &str, seconds: f32) {
+        if self.active.as_ref().is_some_and(|(_, p)| p == path) { return; }
+        let Ok(file) = File::open(path) else {
+            eprintln!("No se pudo abrir la música {}", path);
+            return;
+        };
+        let Ok(dec) = Decoder::new(BufReader::new(file)) else {
+            eprintln!("No se pudo decodificar la música {}", path);
+            return;
+        };
+        let Ok(sink) = Sink::try_new(&self.handle) else { return; };
+        sink.set_volume(0.0);
+        sink.append(dec.repeat_infinite());
+
+        // El saliente anterior (si aún sonaba) se descarta: soltar un Sink lo detiene
+        self.outgoing = self.active.take().map(|(old, _)| {
+            let vol = old.volume();
+            (old, vol)
+        });
+        self.active = Some((sink, path.to_string()));
+        self.mix = Fade::new(0.0);
+        self.mix.set_target(1.0, seconds);
+        self.apply();
+    }
+
+    /// Lleva la ganancia global a `target` en `seconds`.
+    pub fn set_gain(&mut self, target: f32, seconds: f32) {
+        self.gain.set_target(target.max(0.0), seconds);
+    }
+
+    /// Avanza las envolventes y actualiza el volumen de los sinks.
+    pub fn update(&mut self, dt: f32) {
+        self.mix.update(dt);
+        self.gain.update(dt);
+        self.apply();
+        if self.mix.done() { self.outgoing = None; }
+    }
+
+    fn apply(&self) {
+        if let Some((sink, _)) = self.active.as_ref() {
+            sink.set_volume(self.base_volume * self.gain.value * self.mix.value);
+        }
+        if let Some((sink, start)) = self.outgoing.as_ref() {
+            sink.set_volume(start * (1.0 - self.mix.value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fade_moves_linearly_and_retargets() {
+        let mut fade = Fade::new(0.0);
+        fade.set_target(1.0, 1.0);
+        assert!((fade.update(0.25) - 0.25).abs() < 1e-6);
+        assert!(!fade.done());
+        fade.set_target(0.0, 0.5);
+        assert!((fade.update(0.1) - 0.05).abs() < 1e-6);
+        for _ in 0..10 { fade.update(0.1); }
+        assert!(fade.done() && fade.value == 0.0);
+
+        fade.set_target(0.7, 0.0);
+        assert_eq!(fade.value, 0.7);
+    }
+
+    #[test]
+    fn proximity_gain_is_clamped() {
+        assert_eq!(proximity_gain(0.0, 100.0, 0.4), 1.0);
+        assert!((proximity_gain(50.0, 100.0, 0.4) - 0.7).abs() < 1e-6);
+        assert_eq!(proximity_gain(500.0, 100.0, 0.4), 0.4);
+        assert_eq!(proximity_gain(f32::INFINITY, 100.0, 0.4), 0.4);
+    }
+}
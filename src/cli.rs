@@ -0,0 +1,103 @@
+use crate::{GameMode, WIDTH, HEIGHT};
+
+pub const USAGE: &str = "\
+Uso: proyecto-uno [opciones]
+
+Opciones:
+  --seed <n|texto>        semilla base del mapa (un texto se convierte en número)
+  --mode normal|dificil   modo de juego; entra directo a jugar sin pasar por el menú
+  --width <px>            ancho de la ventana (320..=3840)
+  --height <px>           alto de la ventana (240..=2160)
+  --fullscreen            pantalla completa sin bordes (tamaño `display_size` de settings.cfg)
+  -h, --help              muestra esta ayuda";
+
+/// Opciones de línea de comandos; lo que no se indique conserva el valor por defecto.
+pub struct Args {
+    pub seed: Option<u32>,
+    pub mode: Option<GameMode>,
+    pub width: usize,
+    pub height: usize,
+    pub fullscreen: bool,
+}
+
+pub enum ParseError {
+    /// Se pidió `--help`.
+    Help,
+    Invalid(String),
+}
+
+/// Interpreta los argumentos (sin el nombre del programa).
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, ParseError> {
+    let mut out = Args { seed: None, mode: None, width: WIDTH, height: HEIGHT, fullscreen: false };
+    let mut it = args.into_iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Err(ParseError::Help),
+            "--fullscreen" => out.fullscreen = true,
+            "--seed" => out.seed = Some(parse_seed(&value(&mut it, &arg)?)),
+            "--mode" => {
+                let v = value(&mut it, &arg)?;
+                out.mode = Some(match v.to_ascii_lowercase().as_str() {
+                    "normal" => GameMode::Normal,
+                    "dificil" | "difícil" => GameMode::Dificil,
+                    _ => return Err(ParseError::Invalid(format!("modo inválido `{}`", v))),
+                });
+            }
+            "--width" => out.width = parse_dim(&value(&mut it, &arg)?, &arg, 320, 3840)?,
+            "--height" => out.height = parse_dim(&value(&mut it, &arg)?, &arg, 240, 2160)?,
+            _ => return Err(ParseError::Invalid(format!("opción desconocida `{}`", arg))),
+        }
+    }
+    Ok(out)
+}
+
+fn value<I: Iterator<Item = String>>(it: &mut I, flag: &str) -> Result<String, ParseError> {
+    it.next().ok_or_else(|| ParseError::Invalid(format!("falta el valor de `{}`", flag)))
+}
+
+fn parse_dim(v: &str, flag: &str, min: usize, max: usize) -> Result<usize, ParseError> {
+    match v.parse::<usize>() {
+        Ok(n) if (min..=max).contains(&n) => Ok(n),
+        _ => Err(ParseError::Invalid(format!("`{}` debe estar entre {} y {} (recibido `{}`)", flag, min, max, v))),
+    }
+}
+
+/// Número tal cual; cualquier otro texto se convierte con FNV-1a (determinista).
+fn parse_seed(v: &str) -> u32 {
+    v.parse::<u32>().unwrap_or_else(|_| {
+        v.bytes().fold(0x811C9DC5u32, |h, b| (h ^ b as u32).wrapping_mul(0x01000193))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Result<Args, ParseError> {
+        parse(list.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn defaults_and_overrides() {
+        let a = args(&[]).ok().unwrap();
+        assert_eq!((a.width, a.height, a.seed, a.fullscreen), (WIDTH, HEIGHT, None, false));
+
+        let a = args(&["--seed", "7", "--mode", "normal", "--width", "1024", "--height", "768", "--fullscreen"]).ok().unwrap();
+        assert_eq!((a.width, a.height, a.seed, a.fullscreen), (1024, 768, Some(7), true));
+        assert!(a.mode == Some(GameMode::Normal));
+    }
+
+    #[test]
+    fn text_seed_is_deterministic() {
+        assert_eq!(parse_seed("payaso"), parse_seed("payaso"));
+        assert_ne!(parse_seed("payaso"), parse_seed("payasa"));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        for bad in [&["--mode", "facil"][..], &["--width", "10"], &["--height"], &["--bogus"]] {
+            assert!(matches!(args(bad), Err(ParseError::Invalid(_))), "{:?}", bad);
+        }
+        assert!(matches!(args(&["--help"]), Err(ParseError::Help)));
+    }
+}
@@ -1,16 +1,23 @@
+mod audio;
+mod cli;
 mod map;
+mod particles;
 mod player;
 mod raycaster;
 mod render;
+mod settings;
 
 use minifb::{Key, Window, WindowOptions, MouseButton, MouseMode};
-use std::f32::consts::PI;
-use std::time::{Duration, Instant};
+use std::time::Instant;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::BufReader;
-use rodio::{OutputStream, OutputStreamHandle, Sink, Decoder, Source};
+use rodio::{OutputStream, OutputStreamHandle, Sink, Decoder};
+use audio::Music;
 use map::Map;
-use player::Player;
+use particles::ParticlePool;
+use player::{Player, clamp_dt};
+use settings::{Settings, SETTINGS_PATH};
 
 const WIDTH: usize = 800;
 const HEIGHT: usize = 600;
@@ -19,19 +26,60 @@ const OBJ_SWITCH_SECONDS: f32 = 3.0; // intervalo para evaluar si el objetivo ca
 const BGM_PATH: &str = "assets/music/clown_loop.ogg";
 const VICTORY_SFX_PATH: &str = "assets/music/victory_fanfare.ogg";
 const TP_SFX_PATH: &str = "assets/sfx/tp_pop.ogg";
+const KEY_SFX_PATH: &str = "assets/sfx/td_pop.ogg";
+const BREAK_SFX_PATH: &str = "assets/sfx/td_pop.ogg";
+const TEST_LEVEL_PATH: &str = "assets/levels/llave.txt";
+const EDITOR_LEVEL_PATH: &str = "assets/levels/editor.txt";
 const BGM_VOLUME: f32 = 0.35;
+const BGM_CROSSFADE_SECONDS: f32 = 1.5; // fundido cruzado al cambiar de pista
+const INTENSITY_RANGE_TILES: f32 = 16.0; // a partir de esta distancia al objetivo la música queda al mínimo
+const INTENSITY_MIN_GAIN: f32 = 0.45; // ganancia de la música lejos del objetivo
+const INTENSITY_FADE_SECONDS: f32 = 1.2; // la ganancia recorre [0, 1] en este tiempo (sin saltos)
 const SFX_VOLUME: f32 = 1.0;
+const TRANSITION_SECONDS: f32 = 0.4; // duración de la cortina al cambiar de mapa
+const LOOK_SMOOTHING: f32 = 30.0; // 1/s: ritmo al que se aplica el giro acumulado del ratón
+const FRAME_GRAPH_LEN: usize = 120; // cuadros visibles en la gráfica de tiempos (F3)
+const BREAK_REACH_TILES: f32 = 1.5; // alcance de la acción (Espacio) en celdas
+const BREAK_FLASH_SECONDS: f32 = 0.15; // destello al romper un muro
+const BREAK_FLASH_COLOR: u32 = 0xFFF4C0;
+const TP_BURST: usize = 48; // partículas al teletransportarse el objetivo
+const PICKUP_BURST: usize = 32; // partículas al recoger la llave
+const BREAK_BURST: usize = 24; // partículas al romper un muro
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum GameState {
     Menu,
     Playing,
     Victory,
+    Editor,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 enum GameMode { Normal, Dificil }
 
+/// Qué coloca un clic en el editor.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Brush {
+    Cell(u8), // 0 = pasillo, 1..4 = pared, DOOR_ID = puerta
+    Spawn,
+    Objective,
+    Key,
+}
+
+impl Brush {
+    fn label(self) -> String {
+        match self {
+            Brush::Cell(0) => "LIBRE".to_string(),
+            Brush::Cell(map::DOOR_ID) => "PUERTA".to_string(),
+            Brush::Cell(map::BREAKABLE_ID) => "ROMPIBLE".to_string(),
+            Brush::Cell(id) => format!("PARED {}", id),
+            Brush::Spawn => "SPAWN".to_string(),
+            Brush::Objective => "OBJETIVO".to_string(),
+            Brush::Key => "LLAVE".to_string(),
+        }
+    }
+}
+
 fn compute_anchors(map: &Map) -> Vec<(f32, f32)> {
     let w = map.width() as i32;
     let h = map.height() as i32;
@@ -79,35 +127,71 @@ fn find_nearest_free_cell(map: &Map, cx: i32, cy: i32, max_r: i32) -> Option<(i3
     None
 }
 
-fn main() {
-    // Framebuffer
-    let mut buffer = vec![0x000000u32; WIDTH * HEIGHT];
+/// Reproduce un efecto de sonido (si hay audio y el archivo existe).
+fn play_sfx(sink: Option<&Sink>, path: &str) {
+    if let Some(sink) = sink {
+        if let Ok(file) = File::open(path) {
+            if let Ok(dec) = Decoder::new(BufReader::new(file)) {
+                sink.append(dec);
+                sink.set_volume(SFX_VOLUME);
+            }
+        }
+    }
+}
 
+/// Abre la ventana del juego. En pantalla completa es una ventana sin bordes ni título en (0, 0)
+/// del tamaño indicado (minifb no expone la resolución del monitor; ver `display_size`).
+fn open_window(w: usize, h: usize, fullscreen: bool) -> Window {
     let mut window = Window::new(
         "Proyecto Uno - Ray Caster",
-        WIDTH,
-        HEIGHT,
+        w,
+        h,
         WindowOptions {
-            resize: false,
+            resize: !fullscreen,
+            borderless: fullscreen,
+            title: !fullscreen,
+            topmost: fullscreen,
             scale: minifb::Scale::X1,
             ..WindowOptions::default()
         },
     )
     .expect("No se pudo crear la ventana");
+    if fullscreen { window.set_position(0, 0); }
+    window
+}
+
+fn main() {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(cli::ParseError::Help) => { println!("{}", cli::USAGE); return; }
+        Err(cli::ParseError::Invalid(e)) => {
+            eprintln!("error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut settings = Settings::load(SETTINGS_PATH);
+    let windowed_size = (args.width, args.height);
+    let mut fullscreen = args.fullscreen;
+    let (mut screen_w, mut screen_h) = if fullscreen { settings.display_size } else { windowed_size };
 
-    window.limit_update_rate(Some(Duration::from_micros(1_000_000 / 60)));
+    // Framebuffer
+    let mut buffer = vec![0x000000u32; screen_w * screen_h];
+
+    let mut window = open_window(screen_w, screen_h, fullscreen);
+
+    window.limit_update_rate(settings.frame_duration());
 
     // === Audio: stream y sinks
     let mut audio_stream: Option<OutputStream> = None;
     let mut audio_handle: Option<OutputStreamHandle> = None;
-    let mut bgm_sink: Option<Sink> = None;
+    let mut music: Option<Music> = None;
     let mut sfx_sink: Option<Sink> = None;
     if let Ok((stream, handle)) = OutputStream::try_default() {
         audio_stream = Some(stream); // mantener vivo
         audio_handle = Some(handle);
     }
     if let Some(handle) = audio_handle.as_ref() {
-        if let Ok(s) = Sink::try_new(handle) { s.set_volume(BGM_VOLUME); bgm_sink = Some(s); }
+        music = Some(Music::new(handle, BGM_VOLUME));
         if let Ok(s) = Sink::try_new(handle) { s.set_volume(SFX_VOLUME); sfx_sink = Some(s); }
     }
 
@@ -115,53 +199,128 @@ fn main() {
     let mut state = GameState::Menu;
 
     // Modo de juego y selección de menú
-    let mut game_mode = GameMode::Dificil;
-    let mut menu_selected: usize = 1; // 0 = Normal, 1 = Dificil
+    let mut game_mode = args.mode.unwrap_or(GameMode::Dificil);
+    let mut menu_selected: usize = if game_mode == GameMode::Normal { 0 } else { 1 }; // 0 = Normal, 1 = Dificil
+    // Con --mode se entra directo a jugar (como si se pulsara ENTER en el menú)
+    let mut autostart = args.mode.is_some();
 
     // Anclas del objetivo (para modo Normal)
     let mut anchors: Vec<(f32, f32)> = Vec::new();
     let mut anchor_idx: Option<usize> = None;
 
     // Variantes de mapa por semilla
-    let seeds: [u32; 3] = [0, 1, 2];
+    let base_seed = args.seed.unwrap_or(0);
+    let seeds: [u32; 3] = [base_seed, base_seed.wrapping_add(1), base_seed.wrapping_add(2)];
     let mut active_seed_idx: usize = 0;
 
     // Mundo/Jugador
     let mut map = Map::new_with_seed(seeds[active_seed_idx]);
-    let mut player = Player::from_map_spawn(&map);
+    let mut player = Player::from_map_spawn(&map, settings.player_config(game_mode));
 
     // Objetivo (coleccionable)
     let (mut obj_x, mut obj_y) = map.objective_world();
     let mut objective_found = false;
 
+    // Nivel cargado desde ASCII (sin rotación de mapas ni teletransporte del objetivo)
+    let mut custom_level = false;
+    let mut key_pos: Option<(f32, f32)> = None;
+    // Nivel ASCII a arrancar al inicio del próximo cuadro (L en el menú o P en el editor)
+    let mut pending_level: Option<Map> = None;
+
+    // Editor: mapa de trabajo, pincel actual y si la partida en curso es una prueba del editor
+    let mut editor_map = Map::new_with_seed(seeds[0]);
+    let mut brush = Brush::Cell(2);
+    let mut from_editor = false;
+
     let mut last_frame_time = Instant::now();
 
     // FPS
     let mut last_fps_update = Instant::now();
     let mut frame_count: u32 = 0;
     let mut fps: u32 = 0;
-    let mut prev_mouse_x: Option<f32> = None;
+    let mut frame_times: VecDeque<f32> = VecDeque::with_capacity(FRAME_GRAPH_LEN);
+    let mut show_frame_graph = false;
+    // El menú no repinta su título hasta entonces (p. ej. para que se lea un error)
+    let mut menu_title_hold = Instant::now();
+    let mut prev_mouse: Option<(f32, f32)> = None;
+    // Giro pendiente del ratón (yaw, pitch): acumula fracciones de píxel y se integra suavemente
+    let mut look_pending: (f32, f32) = (0.0, 0.0);
     let mut anim_t: f32 = 0.0;
 
-    // Temporizador de cambio de mapa
-    let mut last_switch = Instant::now();
-    let mut last_obj_check = Instant::now();
+    // Temporizadores (segundos acumulados con dt acotado) de cambio de mapa y de objetivo
+    let mut switch_timer: f32 = 0.0;
+    let mut obj_timer: f32 = 0.0;
     let mut rng_state: u32 = 0xA36E_2D4F ^ seeds[active_seed_idx];
 
+    // Transición de cambio de mapa: último cuadro del mapa saliente + tiempo transcurrido
+    let mut transition: Option<(Vec<u32>, f32)> = None;
+    // Tiempo restante del destello al romper un muro
+    let mut break_flash: f32 = 0.0;
+    let mut particles = ParticlePool::new();
+
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        // Delta time 
+        // Delta time (acotado: tras un bloqueo o arrastre de ventana no hay saltos)
         let now = Instant::now();
-        let dt = now.duration_since(last_frame_time).as_secs_f32();
+        let raw_dt = now.duration_since(last_frame_time).as_secs_f32();
+        let dt = clamp_dt(raw_dt);
         last_frame_time = now;
+        if let Some(m) = music.as_mut() { m.update(dt); }
+
+        // Historial de tiempos de cuadro reales (sin acotar) para detectar tirones
+        if frame_times.len() == FRAME_GRAPH_LEN { frame_times.pop_front(); }
+        frame_times.push_back(raw_dt);
+        if window.is_key_pressed(Key::F3, minifb::KeyRepeat::No) { show_frame_graph = !show_frame_graph; }
         anim_t += dt;
 
+        // F11: alternar pantalla completa (sin bordes) recreando la ventana
+        if window.is_key_pressed(Key::F11, minifb::KeyRepeat::No) {
+            fullscreen = !fullscreen;
+            let (w, h) = if fullscreen { settings.display_size } else { windowed_size };
+            window = open_window(w, h, fullscreen);
+            window.limit_update_rate(settings.frame_duration());
+            prev_mouse = None;
+        }
+
+        // Tamaño real de la ventana: el framebuffer y todo el render usan las dimensiones vivas
+        let (win_w, win_h) = window.get_size();
+        if win_w > 0 && win_h > 0 && (win_w, win_h) != (screen_w, screen_h) {
+            screen_w = win_w;
+            screen_h = win_h;
+            buffer = vec![0x000000u32; screen_w * screen_h];
+        }
+
+        // F2: alternar tope de FPS (30/60/120/sin límite)
+        if window.is_key_pressed(Key::F2, minifb::KeyRepeat::No) {
+            settings.cycle_target_fps();
+            window.limit_update_rate(settings.frame_duration());
+            frame_count = 0;
+            last_fps_update = Instant::now();
+        }
+
+        // Arranque de un nivel ASCII: sin rotación de mapas ni teletransporte del objetivo
+        if let Some(level) = pending_level.take() {
+            map = level;
+            player = Player::from_map_spawn(&map, settings.player_config(game_mode));
+            (obj_x, obj_y) = map.objective_world();
+            key_pos = map.key_world();
+            if let Some(m) = music.as_mut() {
+                m.play(BGM_PATH, BGM_CROSSFADE_SECONDS);
+                m.set_gain(1.0, 0.0);
+            }
+            objective_found = false;
+            particles.clear();
+            custom_level = true;
+            state = GameState::Playing;
+            switch_timer = 0.0;
+        }
+
         match state {
             GameState::Menu => {
                 // Limpia el buffer a negro
                 for px in buffer.iter_mut() { *px = 0x000000; }
 
                 // Dibuja menú con botón seleccionado
-                render::draw_menu(&mut buffer, WIDTH, HEIGHT, menu_selected);
+                render::draw_menu(&mut buffer, screen_w, screen_h, menu_selected);
 
                 // Navegación de botones (izq/der)
                 if window.is_key_pressed(Key::Left, minifb::KeyRepeat::No) {
@@ -172,16 +331,17 @@ fn main() {
                 }
 
                 // Enter para jugar
-                if window.is_key_pressed(Key::Enter, minifb::KeyRepeat::No) {
+                if autostart || window.is_key_pressed(Key::Enter, minifb::KeyRepeat::No) {
+                    autostart = false;
                     // Modo según selección actual del menú
                     game_mode = if menu_selected == 0 { GameMode::Normal } else { GameMode::Dificil };
 
                     active_seed_idx = 0;
                     map = Map::new_with_seed(seeds[active_seed_idx]);
-                    player = Player::from_map_spawn(&map);
+                    player = Player::from_map_spawn(&map, settings.player_config(game_mode));
 
                     // Init RNG y temporizador del objetivo antes de colocarlo
-                    last_obj_check = Instant::now();
+                    obj_timer = 0.0;
                     rng_state = 0xA36E_2D4F ^ seeds[active_seed_idx];
                     if rng_state == 0 { rng_state = 0xB5297A4D; }
 
@@ -227,26 +387,60 @@ fn main() {
                         }
                     }
 
-                    // Música de fondo: arrancar loop 
-                    if let Some(sink) = bgm_sink.as_ref() {
-                        if sink.empty() { // no hay nada encolado aún
-                            if let Ok(file) = File::open(BGM_PATH) {
-                                if let Ok(dec) = Decoder::new(BufReader::new(file)) {
-                                    sink.append(dec.repeat_infinite());
-                                }
-                            }
-                            sink.set_volume(BGM_VOLUME);
-                        }
+                    // Música de fondo: pista de la semilla activa (no se reinicia si ya suena)
+                    if let Some(m) = music.as_mut() {
+                        m.play(settings.bgm_path(map.seed(), BGM_PATH), BGM_CROSSFADE_SECONDS);
+                        m.set_gain(1.0, 0.0);
                     }
                     objective_found = false;
+                    particles.clear();
+                    custom_level = false;
+                    key_pos = None;
+                    from_editor = false;
                     state = GameState::Playing;
-                    last_switch = Instant::now();
+                    switch_timer = 0.0;
+                }
+
+                // L: nivel de prueba con llave y puerta
+                if window.is_key_pressed(Key::L, minifb::KeyRepeat::No) {
+                    let loaded = std::fs::read_to_string(TEST_LEVEL_PATH)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| Map::from_ascii(&text));
+                    match loaded {
+                        Ok(level) => { pending_level = Some(level); from_editor = false; }
+                        Err(e) => eprintln!("No se pudo cargar {}: {}", TEST_LEVEL_PATH, e),
+                    }
+                }
+
+                // E: editor de mapas (retoma el último nivel guardado si existe).
+                // Se abre aunque no sea jugable; si no se puede leer, no se entra para no pisarlo con F5.
+                if window.is_key_pressed(Key::E, minifb::KeyRepeat::No) {
+                    let loaded = match std::fs::read_to_string(EDITOR_LEVEL_PATH) {
+                        Ok(text) => Map::parse_ascii(&text),
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Map::new_with_seed(seeds[0])),
+                        Err(e) => Err(e.to_string()),
+                    };
+                    match loaded {
+                        Ok(level) => {
+                            match level.validate() {
+                                Ok(()) => window.set_title("Editor — F5 guardar, P jugar, Retroceso menú"),
+                                Err(e) => window.set_title(&format!("Editor — aviso, no jugable: {}", e)),
+                            }
+                            editor_map = level;
+                            state = GameState::Editor;
+                        }
+                        Err(e) => {
+                            eprintln!("No se pudo abrir {} en el editor: {}", EDITOR_LEVEL_PATH, e);
+                            window.set_title(&format!("No se pudo abrir {}: {}", EDITOR_LEVEL_PATH, e));
+                            menu_title_hold = Instant::now() + std::time::Duration::from_secs(3);
+                        }
+                    }
                 }
 
                 // Click sobre los botones para jugar
                 if window.get_mouse_down(MouseButton::Left) {
                     if let Some((mx, my)) = window.get_mouse_pos(MouseMode::Pass) {
-                        let (r1, r2) = render::menu_button_rects(WIDTH, HEIGHT);
+                        let (r1, r2) = render::menu_button_rects(screen_w, screen_h);
                         let in_rect = |r: (usize, usize, usize, usize), mx: f32, my: f32| -> bool {
                             let (x, y, w, h) = r;
                             mx >= x as f32 && mx < (x + w) as f32 && my >= y as f32 && my < (y + h) as f32
@@ -258,9 +452,9 @@ fn main() {
 
                             active_seed_idx = 0;
                             map = Map::new_with_seed(seeds[active_seed_idx]);
-                            player = Player::from_map_spawn(&map);
+                            player = Player::from_map_spawn(&map, settings.player_config(game_mode));
                             // Init RNG y temporizador
-                            last_obj_check = Instant::now();
+                            obj_timer = 0.0;
                             rng_state = 0xA36E_2D4F ^ seeds[active_seed_idx];
                             if rng_state == 0 { rng_state = 0xB5297A4D; }
 
@@ -304,37 +498,43 @@ fn main() {
                             }
 
                             // Música de fondo
-                            if let Some(sink) = bgm_sink.as_ref() {
-                                if sink.empty() {
-                                    if let Ok(file) = File::open(BGM_PATH) {
-                                        if let Ok(dec) = Decoder::new(BufReader::new(file)) {
-                                            sink.append(dec.repeat_infinite());
-                                        }
-                                    }
-                                    sink.set_volume(BGM_VOLUME);
-                                }
+                            if let Some(m) = music.as_mut() {
+                                m.play(settings.bgm_path(map.seed(), BGM_PATH), BGM_CROSSFADE_SECONDS);
+                                m.set_gain(1.0, 0.0);
                             }
 
                             objective_found = false;
+                            particles.clear();
+                            custom_level = false;
+                            key_pos = None;
+                            from_editor = false;
                             state = GameState::Playing;
-                            last_switch = Instant::now();
+                            switch_timer = 0.0;
                         }
                     }
                 }
 
                 // Título (instrucciones)
-                if last_fps_update.elapsed().as_secs_f32() >= 0.5 {
+                if last_fps_update.elapsed().as_secs_f32() >= 0.5 && Instant::now() >= menu_title_hold {
                     window.set_title("Menú — Clic en JUGAR o ENTER");
                     last_fps_update = Instant::now();
                 }
 
                 // No mouse-look en menú
-                prev_mouse_x = None;
+                prev_mouse = None;
+                look_pending = (0.0, 0.0);
+                transition = None;
             }
             GameState::Playing => {
+                // FOV horizontal según el aspecto actual (la ventana puede cambiar de tamaño)
+                player.fit_screen(screen_w, screen_h);
+                switch_timer += dt;
+                obj_timer += dt;
+
                 // Reubicación del objetivo con probabilidad 50% cada OBJ_SWITCH_SECONDS
-                if !objective_found && last_obj_check.elapsed().as_secs_f32() >= OBJ_SWITCH_SECONDS {
+                if !objective_found && !custom_level && obj_timer >= OBJ_SWITCH_SECONDS {
                     let mut did_teleport = false;
+                    let (old_x, old_y) = (obj_x, obj_y);
                     // xorshift32 determinista
                     rng_state ^= rng_state << 13; rng_state ^= rng_state >> 17; rng_state ^= rng_state << 5;
                     let coin = rng_state & 1; // 0 o 1 con ~50%
@@ -389,6 +589,7 @@ fn main() {
                         }
                     }
                     if did_teleport {
+                        particles.burst(old_x, old_y, 0.0, TP_BURST, anim_t);
                         if let Some(sink) = sfx_sink.as_ref() {
                             if let Ok(file) = File::open(TP_SFX_PATH) {
                                 if let Ok(dec) = Decoder::new(BufReader::new(file)) {
@@ -398,11 +599,11 @@ fn main() {
                             }
                         }
                     }
-                    last_obj_check = Instant::now();
+                    obj_timer = 0.0;
                 }
 
                 // Cambio de mapa cada SWITCH_SECONDS
-                if last_switch.elapsed().as_secs_f32() >= SWITCH_SECONDS {
+                if !custom_level && switch_timer >= SWITCH_SECONDS {
                     active_seed_idx = (active_seed_idx + 1) % seeds.len();
                     let new_map = Map::new_with_seed(seeds[active_seed_idx]);
 
@@ -422,6 +623,10 @@ fn main() {
 
                     // Nuevo objetivo para la nueva variante
                     map = new_map;
+                    // Pista de la nueva semilla con fundido cruzado (misma pista = sin cambios)
+                    if let Some(m) = music.as_mut() {
+                        m.play(settings.bgm_path(map.seed(), BGM_PATH), BGM_CROSSFADE_SECONDS);
+                    }
                     // Reposicionar objetivo según modo para la nueva variante
                     match game_mode {
                         GameMode::Normal => {
@@ -471,46 +676,96 @@ fn main() {
                         }
                     }
                     objective_found = false;
-                    last_obj_check = Instant::now(); rng_state ^= seeds[active_seed_idx] ^ 0x9E3779B1; if rng_state == 0 { rng_state = 0x68E31DA4; }
-                    last_switch = Instant::now();
+                    particles.clear();
+                    obj_timer = 0.0; rng_state ^= seeds[active_seed_idx] ^ 0x9E3779B1; if rng_state == 0 { rng_state = 0x68E31DA4; }
+                    switch_timer = 0.0;
+
+                    // El buffer aún contiene el último cuadro del mapa anterior: la cortina lo retira
+                    if settings.map_transition {
+                        transition = Some((buffer.clone(), 0.0));
+                    }
+                }
+
+                // Durante la cortina de cambio de mapa no hay input (la recolocación queda oculta)
+                if let Some((prev, t)) = transition.as_mut() {
+                    *t += dt;
+                    if *t >= TRANSITION_SECONDS || prev.len() != buffer.len() { transition = None; }
                 }
+                let input_enabled = transition.is_none();
 
                 // Input movimiento/rotación 
-                if window.is_key_down(Key::W) { player.forward_collide(dt, &map); }
-                if window.is_key_down(Key::S) { player.backward_collide(dt, &map); }
-                if window.is_key_down(Key::A) { player.strafe_left_collide(dt, &map); }
-                if window.is_key_down(Key::D) { player.strafe_right_collide(dt, &map); }
-                if window.is_key_down(Key::Q) { player.turn_left(dt); }
-                if window.is_key_down(Key::E) { player.turn_right(dt); }
-                if window.is_key_down(Key::Left) { player.turn_left(dt); }
-                if window.is_key_down(Key::Right) { player.turn_right(dt); }
+                if input_enabled && window.is_key_down(Key::W) { player.forward_collide(dt, &map); }
+                if input_enabled && window.is_key_down(Key::S) { player.backward_collide(dt, &map); }
+                if input_enabled && window.is_key_down(Key::A) { player.strafe_left_collide(dt, &map); }
+                if input_enabled && window.is_key_down(Key::D) { player.strafe_right_collide(dt, &map); }
+                if input_enabled && window.is_key_down(Key::Q) { player.turn_left(dt); }
+                if input_enabled && window.is_key_down(Key::E) { player.turn_right(dt); }
+                if input_enabled && window.is_key_down(Key::Left) { player.turn_left(dt); }
+                if input_enabled && window.is_key_down(Key::Right) { player.turn_right(dt); }
 
                 // Mouse drag-to-look mientras está presionado el botón izquierdo
-                if window.get_mouse_down(MouseButton::Left) {
-                    if let Some((mx, _my)) = window.get_mouse_pos(MouseMode::Pass) {
-                        if let Some(prev) = prev_mouse_x {
-                            let dx = mx - prev;
-                            let sensitivity: f32 = 0.004;
-                            player.angle += dx as f32 * sensitivity;
-                            while player.angle >= PI { player.angle -= 2.0 * PI; }
-                            while player.angle < -PI { player.angle += 2.0 * PI; }
+                if input_enabled && window.get_mouse_down(MouseButton::Left) {
+                    if let Some((mx, my)) = window.get_mouse_pos(MouseMode::Pass) {
+                        if let Some((prev_x, prev_y)) = prev_mouse {
+                            look_pending.0 += (mx - prev_x) * settings.mouse_sens_x;
+                            // Arrastrar hacia arriba mira arriba (salvo invert_y)
+                            let dy = if settings.invert_y { my - prev_y } else { prev_y - my };
+                            look_pending.1 += dy * settings.mouse_sens_y;
                         }
-                        prev_mouse_x = Some(mx);
+                        prev_mouse = Some((mx, my));
                     } else {
-                        prev_mouse_x = None;
+                        prev_mouse = None;
                     }
                 } else {
-                    prev_mouse_x = None;
+                    prev_mouse = None;
                 }
 
+                // Integra una fracción del giro pendiente (suavizado exponencial, independiente del FPS):
+                // los saltos de píxel del ratón se reparten entre cuadros y el resto se conserva.
+                let k = 1.0 - (-LOOK_SMOOTHING * dt).exp();
+                let (yaw, pitch) = (look_pending.0 * k, look_pending.1 * k);
+                player.rotate(yaw);
+                player.look_vertical(pitch);
+                look_pending.0 -= yaw;
+                look_pending.1 -= pitch;
+
+                // Acción (Espacio, solo modo normal): rompe el muro rompible que esté justo delante.
+                // `set_cell` invalida las distancias cacheadas del mapa.
+                if input_enabled && game_mode == GameMode::Normal && window.is_key_pressed(Key::Space, minifb::KeyRepeat::No) {
+                    let hit = raycaster::cast_forward(&map, &player);
+                    let reach = map.tile_size() as f32 * BREAK_REACH_TILES;
+                    if hit.wall_id > 0 && hit.dist_px <= reach && map.is_breakable(hit.cell.0, hit.cell.1) && map.set_cell(hit.cell.0, hit.cell.1, 0) {
+                        break_flash = BREAK_FLASH_SECONDS;
+                        if let Some((wx, wy)) = map.cell_center_world(hit.cell.0, hit.cell.1) {
+                            particles.burst(wx, wy, 0.0, BREAK_BURST, anim_t);
+                        }
+                        play_sfx(sfx_sink.as_ref(), BREAK_SFX_PATH);
+                    }
+                }
+                break_flash = (break_flash - dt).max(0.0);
+                particles.update(dt, -(map.tile_size() as f32) * 0.5);
+
+                // Recogida de la llave: abre las puertas
+                if let Some((kx, ky)) = key_pos {
+                    let dx = player.x - kx;
+                    let dy = player.y - ky;
+                    let pick_r = map.tile_size() as f32 * 0.7;
+                    if dx * dx + dy * dy <= pick_r * pick_r {
+                        player.has_key = true;
+                        key_pos = None;
+                        particles.burst(player.x, player.y, 0.0, PICKUP_BURST, anim_t);
+                        play_sfx(sfx_sink.as_ref(), KEY_SFX_PATH);
+                    }
+                }
+
+                // Distancia al objetivo: recogida, intensidad de la música y título
+                let obj_dist = (player.x - obj_x).hypot(player.y - obj_y);
+
                 // Detección de recogida del objetivo (radio amplio ~0.7 * TILE_SIZE para "atravesarlo")
                 if !objective_found {
-                    let dx = player.x - obj_x;
-                    let dy = player.y - obj_y;
-                    let dist2 = dx * dx + dy * dy;
                     let pick_r = map.tile_size() as f32 * 0.7;
-                    if dist2 <= pick_r * pick_r {
-                        if let Some(sink) = bgm_sink.as_ref() { sink.set_volume(BGM_VOLUME * 0.2); }
+                    if obj_dist <= pick_r {
+                        if let Some(m) = music.as_mut() { m.set_gain(0.2, 0.3); }
                         if let Some(sink) = sfx_sink.as_ref() {
                             if let Ok(file) = File::open(VICTORY_SFX_PATH) {
                                 if let Ok(dec) = Decoder::new(BufReader::new(file)) { sink.append(dec); }
@@ -522,10 +777,43 @@ fn main() {
                     }
                 }
 
+                // Intensidad de la música: más alta cerca del objetivo ("frío/caliente").
+                // La envolvente suaviza los saltos (p. ej. cuando el objetivo se teletransporta lejos).
+                if let Some(m) = music.as_mut().filter(|_| !objective_found) {
+                    let range = map.tile_size() as f32 * INTENSITY_RANGE_TILES;
+                    m.set_gain(audio::proximity_gain(obj_dist, range, INTENSITY_MIN_GAIN), INTENSITY_FADE_SECONDS);
+                }
+
+                // F4: volver al editor si la partida es una prueba del editor
+                if from_editor && window.is_key_pressed(Key::F4, minifb::KeyRepeat::No) {
+                    state = GameState::Editor;
+                    window.set_title("Editor — F5 guardar, P jugar, Retroceso menú");
+                }
+
                 // Render escena completa + minimapa
-                render::draw_scene(&mut buffer, WIDTH, HEIGHT, &map, &player, obj_x, obj_y, anim_t);
-                render::draw_minimap(&mut buffer, WIDTH, HEIGHT, &map, &player, obj_x, obj_y, anim_t);
-                render::draw_fps_hud(&mut buffer, WIDTH, HEIGHT, fps);
+                let scene = render::Scene {
+                    map: &map,
+                    player: &player,
+                    objective: (obj_x, obj_y),
+                    key: key_pos,
+                    particles: particles.particles(),
+                    anim_t,
+                    ray_step: settings.ray_step,
+                };
+                render::draw_scene(&mut buffer, screen_w, screen_h, &scene);
+                render::draw_minimap(&mut buffer, screen_w, screen_h, &scene);
+                if break_flash > 0.0 {
+                    let alpha = 0.35 * break_flash / BREAK_FLASH_SECONDS;
+                    render::blend_rect(&mut buffer, screen_w, screen_h, (0, 0, screen_w, screen_h), BREAK_FLASH_COLOR, alpha);
+                }
+                if let Some((prev, t)) = transition.as_ref() {
+                    render::draw_wipe(&mut buffer, screen_w, screen_h, prev, t / TRANSITION_SECONDS);
+                }
+                render::draw_fps_hud(&mut buffer, screen_w, screen_h, fps, settings.target_fps);
+                render::draw_key_hud(&mut buffer, screen_w, screen_h, player.has_key);
+                if show_frame_graph {
+                    render::draw_frame_graph(&mut buffer, screen_w, screen_h, frame_times.make_contiguous(), settings.target_fps);
+                }
 
                 // Actualiza FPS cada 1s + título (incluye estado del objetivo y distancia)
                 frame_count += 1;
@@ -534,21 +822,76 @@ fn main() {
                     frame_count = 0;
                     last_fps_update = Instant::now();
 
-                    let dx = player.x - obj_x;
-                    let dy = player.y - obj_y;
-                    let dist = (dx * dx + dy * dy).sqrt();
                     let obj_txt = if objective_found { "OBJ: 1/1" } else { "OBJ: 0/1" };
+                    let key_txt = if player.has_key { " | LLAVE" } else { "" };
 
                     window.set_title(&format!(
-                        "Proyecto Uno - Ray Caster | {} FPS | seed:{} | {} | dist:{:.0} | x:{:.1} y:{:.1} ang:{:.1}°",
-                        fps, map.seed(), obj_txt, dist, player.x, player.y, player.angle.to_degrees()
+                        "Proyecto Uno - Ray Caster | {} FPS | seed:{} | {}{} | dist:{:.0} | x:{:.1} y:{:.1} ang:{:.1}°",
+                        fps, map.seed(), obj_txt, key_txt, obj_dist, player.x, player.y, player.angle.to_degrees()
                     ));
                 }
             }
+            GameState::Editor => {
+                // Pincel: 0..5 IDs de celda (5 = puerta), S/O/K marcadores
+                let brush_keys = [
+                    (Key::Key0, Brush::Cell(0)), (Key::Key1, Brush::Cell(1)), (Key::Key2, Brush::Cell(2)),
+                    (Key::Key3, Brush::Cell(3)), (Key::Key4, Brush::Cell(4)), (Key::Key5, Brush::Cell(map::DOOR_ID)),
+                    (Key::S, Brush::Spawn), (Key::O, Brush::Objective), (Key::K, Brush::Key),
+                ];
+                for (k, b) in brush_keys {
+                    if window.is_key_pressed(k, minifb::KeyRepeat::No) { brush = b; }
+                }
+
+                // Clic izquierdo pinta (se puede arrastrar); clic derecho deja pasillo.
+                // El perímetro no se puede abrir: `set_cell` lo rechaza.
+                let hover = window.get_mouse_pos(MouseMode::Pass)
+                    .and_then(|(mx, my)| render::editor_cell_at(screen_w, screen_h, &editor_map, mx, my));
+                if let Some((cx, cy)) = hover {
+                    if window.get_mouse_down(MouseButton::Left) {
+                        match brush {
+                            Brush::Cell(id) => editor_map.set_cell(cx, cy, id),
+                            Brush::Spawn => editor_map.set_spawn(cx, cy),
+                            Brush::Objective => editor_map.set_objective(cx, cy),
+                            Brush::Key => editor_map.set_key(cx, cy),
+                        };
+                    } else if window.get_mouse_down(MouseButton::Right) {
+                        editor_map.set_cell(cx, cy, 0);
+                    }
+                }
+
+                // F5: guardar en formato ASCII (también a medio hacer, avisando si aún no es jugable)
+                if window.is_key_pressed(Key::F5, minifb::KeyRepeat::No) {
+                    match std::fs::write(EDITOR_LEVEL_PATH, editor_map.to_ascii()) {
+                        Ok(()) => match editor_map.validate() {
+                            Ok(()) => window.set_title(&format!("Editor — guardado en {}", EDITOR_LEVEL_PATH)),
+                            Err(e) => window.set_title(&format!("Editor — guardado, pero no jugable: {}", e)),
+                        },
+                        Err(e) => {
+                            eprintln!("No se pudo guardar {}: {}", EDITOR_LEVEL_PATH, e);
+                            window.set_title(&format!("Editor — no se pudo guardar: {}", e));
+                        }
+                    }
+                }
+
+                // P: probar el mapa (pasa por el cargador ASCII para validar spawn/llave/objetivo)
+                if window.is_key_pressed(Key::P, minifb::KeyRepeat::No) {
+                    match Map::from_ascii(&editor_map.to_ascii()) {
+                        Ok(level) => { pending_level = Some(level); from_editor = true; }
+                        Err(e) => window.set_title(&format!("Editor — no jugable: {}", e)),
+                    }
+                }
+
+                if window.is_key_pressed(Key::Backspace, minifb::KeyRepeat::No) {
+                    state = GameState::Menu;
+                    window.set_title("Menú — Clic en JUGAR o ENTER");
+                }
+
+                render::draw_editor(&mut buffer, screen_w, screen_h, &editor_map, hover, &brush.label());
+            }
             GameState::Victory => {
                 // Mostrar pantalla de victoria; no hay input de juego ni cambio de mapa
                 for px in buffer.iter_mut() { *px = 0x000000; }
-                render::draw_victory(&mut buffer, WIDTH, HEIGHT);
+                render::draw_victory(&mut buffer, screen_w, screen_h);
 
                 // Volver al menú
                 if window.is_key_pressed(Key::Enter, minifb::KeyRepeat::No) || window.get_mouse_down(MouseButton::Left) {
@@ -559,7 +902,7 @@ fn main() {
         }
 
         window
-            .update_with_buffer(&buffer, WIDTH, HEIGHT)
+            .update_with_buffer(&buffer, screen_w, screen_h)
             .expect("No se pudo actualizar el framebuffer");
     }
 }
\ No newline at end of file
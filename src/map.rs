@@ -1,12 +1,26 @@
+use std::cell::OnceCell;
+use std::collections::VecDeque;
+
+#[derive(Clone)]
 pub struct Map {
     tile_size: u32,
     grid: Vec<Vec<u8>>, // 0 = libre, >0 = pared (ID)
     seed: u32,
+    width: usize,
+    height: usize,
+    spawn: (i32, i32),
+    objective: Option<(i32, i32)>, // fijado por nivel ASCII; None = calculado
+    key: Option<(i32, i32)>,
+    spawn_field: OnceCell<Vec<Option<u32>>>, // distancias desde el spawn; se vacía al editar
 }
 
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 64;
 pub const TILE_SIZE: u32 = 40;
+/// Puerta cerrada: sólida hasta que el jugador tenga la llave.
+pub const DOOR_ID: u8 = 5;
+/// Muro que el jugador puede romper con la acción (Espacio).
+pub const BREAKABLE_ID: u8 = 4;
 
 impl Map {
     /// Variante por defecto (seed = 0)
@@ -14,16 +28,183 @@ impl Map {
 
     /// Crea un mapa variando la semilla. Mapas con semillas distintas generan laberintos distintos.
     pub fn new_with_seed(seed: u32) -> Self {
-        Self { tile_size: TILE_SIZE, grid: build_grid(seed), seed }
+        Self {
+            tile_size: TILE_SIZE,
+            grid: build_grid(seed),
+            seed,
+            width: WIDTH,
+            height: HEIGHT,
+            spawn: (1, 1),
+            objective: None,
+            key: None,
+            spawn_field: OnceCell::new(),
+        }
     }
 
-    pub fn width(&self) -> usize { WIDTH }
-    pub fn height(&self) -> usize { HEIGHT }
+    /// Carga un nivel desde texto ASCII (una fila por línea, todas del mismo ancho).
+    /// - `#`: muro firme (ID=1); `1`..`9`: pared con ese ID. Algunos IDs tienen comportamiento:
+    ///   `4` es rompible (`BREAKABLE_ID`) y `5` es una puerta (`DOOR_ID`, igual que `D`)
+    /// - `.` o espacio: pasillo
+    /// - `S`: spawn, `O`: objetivo, `K`: llave (todas sobre pasillo)
+    /// - `D`: puerta cerrada (`DOOR_ID`)
+    ///
+    /// Sin `S` el spawn es (1, 1); en cualquier caso debe ser un pasillo dentro del mapa.
+    /// Además se exige que el nivel sea jugable (ver `validate`).
+    pub fn from_ascii(text: &str) -> Result<Self, String> {
+        let map = Self::parse_ascii(text)?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Como `from_ascii` pero sólo comprueba la forma (caracteres, filas, spawn dentro del mapa),
+    /// no que sea jugable: el editor abre así niveles a medio hacer sin perderlos.
+    pub fn parse_ascii(text: &str) -> Result<Self, String> {
+        let mut grid: Vec<Vec<u8>> = Vec::new();
+        let mut spawn = None;
+        let mut objective = None;
+        let mut key = None;
+        for (y, line) in text.lines().map(|l| l.trim_end()).filter(|l| !l.is_empty()).enumerate() {
+            let mut row = Vec::with_capacity(line.len());
+            for (x, ch) in line.chars().enumerate() {
+                let cell = (x as i32, y as i32);
+                let id = match ch {
+                    '#' => 1,
+                    '1'..='9' => ch as u8 - b'0',
+                    '.' | ' ' => 0,
+                    'S' => { spawn = Some(cell); 0 }
+                    'O' => { objective = Some(cell); 0 }
+                    'K' => { key = Some(cell); 0 }
+                    'D' => DOOR_ID,
+                    _ => return Err(format!("carácter inválido '{}' en ({}, {})", ch, x, y)),
+                };
+                row.push(id);
+            }
+            if let Some(first) = grid.first().filter(|first| first.len() != row.len()) {
+                return Err(format!("la fila {} mide {} y se esperaban {}", y, row.len(), first.len()));
+            }
+            grid.push(row);
+        }
+        if grid.is_empty() { return Err("nivel vacío".to_string()); }
+
+        let spawn = spawn.unwrap_or((1, 1));
+        let (sx, sy) = spawn;
+        if grid.get(sy as usize).and_then(|row| row.get(sx as usize)).is_none() {
+            return Err(format!("el spawn ({}, {}) queda fuera del mapa", sx, sy));
+        }
+
+        let width = grid[0].len();
+        let height = grid.len();
+        Ok(Self {
+            tile_size: TILE_SIZE,
+            grid,
+            seed: 0,
+            width,
+            height,
+            spawn,
+            objective,
+            key,
+            spawn_field: OnceCell::new(),
+        })
+    }
+
+    /// ¿Se puede jugar? El borde debe ser muro (ni pasillo ni puerta), el spawn pasillo,
+    /// la llave alcanzable sin abrir puertas y el objetivo al menos con ellas abiertas.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(((cx, cy), _)) = self.cells().find(|&((cx, cy), id)| (id == 0 || id == DOOR_ID) && self.is_perimeter(cx, cy)) {
+            return Err(format!("el borde del mapa debe ser muro (celda {}, {})", cx, cy));
+        }
+        let (sx, sy) = self.spawn;
+        if !self.is_free(sx, sy) {
+            return Err(format!("el spawn ({}, {}) debe ser un pasillo dentro del mapa", sx, sy));
+        }
+        let closed = bfs_distances(&self.grid, self.spawn, |id| id == 0);
+        let open = bfs_distances(&self.grid, self.spawn, |id| id == 0 || id == DOOR_ID);
+        let idx = |(x, y): (i32, i32)| y as usize * self.width + x as usize;
+        if self.key.is_some_and(|k| closed[idx(k)].is_none()) {
+            return Err("la llave no es alcanzable desde el spawn".to_string());
+        }
+        if self.objective.is_some_and(|o| open[idx(o)].is_none()) {
+            return Err("el objetivo no es alcanzable desde el spawn".to_string());
+        }
+        Ok(())
+    }
+
+    /// Exporta al formato de `from_ascii` (spawn, objetivo y llave incluidos).
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        let objective = self.objective_cell();
+        for (cell, id) in self.cells() {
+            let ch = if cell == self.spawn { 'S' }
+                else if cell == objective { 'O' }
+                else if Some(cell) == self.key { 'K' }
+                else {
+                    match id {
+                        0 => '.',
+                        1 => '#',
+                        DOOR_ID => 'D',
+                        _ => char::from(b'0' + id.min(9)),
+                    }
+                };
+            out.push(ch);
+            if cell.0 as usize == self.width - 1 { out.push('\n'); }
+        }
+        out
+    }
+
+    /// Escribe el ID de una celda y devuelve si se aplicó.
+    /// No se pintan paredes sobre spawn, objetivo o llave (hay que moverlos antes): `to_ascii`
+    /// escribe el marcador y la pared se perdería al guardar. Se rechaza fuera de rango y abrir el perímetro (pasillo o puerta en el borde: con la llave
+    /// la puerta se atraviesa), para que el DDA y el BFS nunca salgan del mapa.
+    /// Invalida las distancias cacheadas.
+    pub fn set_cell(&mut self, cx: i32, cy: i32, id: u8) -> bool {
+        if !self.in_bounds(cx, cy) { return false; }
+        if (id == 0 || id == DOOR_ID) && self.is_perimeter(cx, cy) { return false; }
+        if id != 0 && self.is_marker((cx, cy)) { return false; }
+        self.grid[cy as usize][cx as usize] = id;
+        self.spawn_field.take();
+        true
+    }
+
+    /// Coloca spawn, objetivo o llave en una celda (que pasa a ser pasillo).
+    /// Mismas reglas que `set_cell(.., 0)`; devuelve si se aplicó.
+    pub fn set_spawn(&mut self, cx: i32, cy: i32) -> bool {
+        if !self.set_cell(cx, cy, 0) { return false; }
+        self.spawn = (cx, cy);
+        true
+    }
+
+    pub fn set_objective(&mut self, cx: i32, cy: i32) -> bool {
+        if !self.set_cell(cx, cy, 0) { return false; }
+        self.objective = Some((cx, cy));
+        true
+    }
+
+    pub fn set_key(&mut self, cx: i32, cy: i32) -> bool {
+        if !self.set_cell(cx, cy, 0) { return false; }
+        self.key = Some((cx, cy));
+        true
+    }
+
+    /// ¿Hay spawn, objetivo fijo o llave en la celda?
+    fn is_marker(&self, cell: (i32, i32)) -> bool {
+        cell == self.spawn || self.objective == Some(cell) || self.key == Some(cell)
+    }
+
+    /// ¿La celda está en el borde del mapa?
+    fn is_perimeter(&self, cx: i32, cy: i32) -> bool {
+        cx == 0 || cy == 0 || cx == self.width as i32 - 1 || cy == self.height as i32 - 1
+    }
+
+    /// Celda de spawn.
+    pub fn spawn_cell(&self) -> (i32, i32) { self.spawn }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
     pub fn tile_size(&self) -> u32 { self.tile_size }
     pub fn seed(&self) -> u32 { self.seed }
 
     pub fn in_bounds(&self, cx: i32, cy: i32) -> bool {
-        cx >= 0 && cy >= 0 && (cx as usize) < WIDTH && (cy as usize) < HEIGHT
+        cx >= 0 && cy >= 0 && (cx as usize) < self.width && (cy as usize) < self.height
     }
 
     pub fn cell_id(&self, cx: i32, cy: i32) -> Option<u8> {
@@ -31,6 +212,20 @@ impl Map {
         Some(self.grid[cy as usize][cx as usize])
     }
 
+    /// Recorre todas las celdas por filas como `((cx, cy), id)`, sin exponer el grid.
+    /// Ejemplo de uso en el test `cells_and_rows_match_cell_id`.
+    pub fn cells(&self) -> impl Iterator<Item = ((i32, i32), u8)> + '_ {
+        self.grid.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().map(move |(x, &id)| ((x as i32, y as i32), id))
+        })
+    }
+
+    /// Fila `cy` del grid (solo lectura); `None` si está fuera de rango.
+    pub fn row(&self, cy: i32) -> Option<&[u8]> {
+        if cy < 0 { return None; }
+        self.grid.get(cy as usize).map(|row| row.as_slice())
+    }
+
     pub fn is_wall(&self, cx: i32, cy: i32) -> bool {
         self.cell_id(cx, cy).map(|id| id > 0).unwrap_or(false)
     }
@@ -50,9 +245,28 @@ impl Map {
         Some((x, y))
     }
 
-    /// Punto de spawn recomendado, esquina NW del laberinto (celda libre 1,1)
+    /// Punto de spawn recomendado, esquina NW del laberinto (celda libre 1,1) o `S` del nivel.
     pub fn recommended_spawn(&self) -> (f32, f32) {
-        self.cell_center_world(1, 1).unwrap()
+        let (sx, sy) = self.spawn;
+        self.cell_center_world(sx, sy).unwrap()
+    }
+
+    /// ¿La celda es una puerta cerrada?
+    pub fn is_door(&self, cx: i32, cy: i32) -> bool {
+        matches!(self.cell_id(cx, cy), Some(DOOR_ID))
+    }
+
+    /// Celda de la llave, si el nivel tiene una.
+    pub fn key_cell(&self) -> Option<(i32, i32)> { self.key }
+
+    /// Centro de la llave en coordenadas de mundo (píxeles).
+    pub fn key_world(&self) -> Option<(f32, f32)> {
+        self.key_cell().and_then(|(cx, cy)| self.cell_center_world(cx, cy))
+    }
+
+    /// ¿La celda es un muro rompible?
+    pub fn is_breakable(&self, cx: i32, cy: i32) -> bool {
+        self.cell_id(cx, cy) == Some(BREAKABLE_ID)
     }
 
     /// ¿La celda es libre (pasillo)?
@@ -60,17 +274,23 @@ impl Map {
         matches!(self.cell_id(cx, cy), Some(0))
     }
 
+    /// Campo de distancias por pasillo (BFS 4-vecinos) desde `from`.
+    /// Índice `y * width + x`; `None` si la celda es muro o no es alcanzable.
+    pub fn distance_field(&self, from: (i32, i32)) -> Vec<Option<u32>> {
+        bfs_distances(&self.grid, from, |id| id == 0)
+    }
+
     /// Devuelve la celda objetivo (determinística por seed), lejos del spawn.
-    /// Elige una celda libre maximizando distancia al spawn con un pequeño jitter por hash.
+    /// Elige la celda libre con mayor distancia **por pasillo** al spawn, con un pequeño jitter por hash.
     pub fn objective_cell(&self) -> (i32, i32) {
-        let (sx, sy) = (1i32, 1i32); // spawn en celda (1,1)
+        if let Some(cell) = self.objective { return cell; }
+        let (sx, sy) = self.spawn;
+        let field = self.spawn_field.get_or_init(|| self.distance_field((sx, sy)));
         let mut best = (sx, sy);
         let mut best_score: i64 = i64::MIN;
-        for y in 1..(HEIGHT as i32 - 1) {
-            for x in 1..(WIDTH as i32 - 1) {
-                if !self.is_free(x, y) { continue; }
-                let dx = x - sx; let dy = y - sy;
-                let d2 = (dx as i64 * dx as i64) + (dy as i64 * dy as i64);
+        for y in 1..(self.height as i32 - 1) {
+            for x in 1..(self.width as i32 - 1) {
+                let Some(d) = field[y as usize * self.width + x as usize] else { continue; };
                 // hash determinista con seed para desempatar
                 let mut h = self.seed
                     ^ (x as u32).wrapping_mul(73856093)
@@ -78,7 +298,7 @@ impl Map {
                     ^ 0x9E3779B9;
                 h ^= h << 13; h ^= h >> 17; h ^= h << 5;
                 let jitter = (h & 0xFF) as i64; // 0..255
-                let score = d2 * 256 + jitter;
+                let score = d as i64 * 256 + jitter;
                 if score > best_score { best_score = score; best = (x, y); }
             }
         }
@@ -97,6 +317,7 @@ impl Map {
 /// - Muros internos: ID=2
 /// - Pasillos: 0
 /// - Pilares decorativos: ID=3
+/// - Muros rompibles (`BREAKABLE_ID`): algunos muros internos entre dos pasillos (atajos)
 fn build_grid(seed: u32) -> Vec<Vec<u8>> {
     // Base: todo muro interno (2) y perímetro (1)
     let mut g = vec![vec![2u8; WIDTH]; HEIGHT];
@@ -201,5 +422,289 @@ fn build_grid(seed: u32) -> Vec<Vec<u8>> {
         for (x, y) in add { g[y][x] = 3; }
     }
 
+    // Muros rompibles: muros internos con pasillo a ambos lados; densidad ≈ 1/8
+    {
+        let mut mark: Vec<(usize, usize)> = Vec::new();
+        for y in 1..HEIGHT - 1 {
+            for x in 1..WIDTH - 1 {
+                if g[y][x] != 2 { continue; }
+                let horizontal = g[y][x - 1] == 0 && g[y][x + 1] == 0;
+                let vertical = g[y - 1][x] == 0 && g[y + 1][x] == 0;
+                if !(horizontal || vertical) { continue; }
+                let mut h = seed
+                    ^ (x as u32).wrapping_mul(0x85EBCA77)
+                    ^ (y as u32).wrapping_mul(0xC2B2AE3D);
+                h ^= h << 13; h ^= h >> 17; h ^= h << 5;
+                if h.is_multiple_of(8) { mark.push((x, y)); }
+            }
+        }
+        for (x, y) in mark { g[y][x] = BREAKABLE_ID; }
+    }
+
+    // Los pilares pueden aislar bolsillos de pasillo: reconectarlos con el componente del spawn
+    reconnect_pockets(&mut g, (sx as i32, sy as i32));
+
     g
-}
\ No newline at end of file
+}
+
+/// BFS 4-vecinos sobre el grid desde `from`, avanzando por celdas con `passable(id)`.
+/// Índice `y * ancho + x`; `None` = no alcanzable (o no transitable).
+fn bfs_distances(g: &[Vec<u8>], from: (i32, i32), passable: impl Fn(u8) -> bool) -> Vec<Option<u32>> {
+    let h = g.len();
+    let w = g.first().map(|row| row.len()).unwrap_or(0);
+    let cell = |x: i32, y: i32| -> Option<u8> {
+        if x < 0 || y < 0 || x as usize >= w || y as usize >= h { return None; }
+        Some(g[y as usize][x as usize])
+    };
+
+    let mut dist: Vec<Option<u32>> = vec![None; w * h];
+    let (fx, fy) = from;
+    if !cell(fx, fy).is_some_and(&passable) { return dist; }
+
+    let mut queue: VecDeque<(i32, i32)> = VecDeque::new();
+    dist[fy as usize * w + fx as usize] = Some(0);
+    queue.push_back(from);
+    while let Some((x, y)) = queue.pop_front() {
+        let d = dist[y as usize * w + x as usize].unwrap_or(0);
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let (nx, ny) = (x + dx, y + dy);
+            if !cell(nx, ny).is_some_and(&passable) { continue; }
+            let idx = ny as usize * w + nx as usize;
+            if dist[idx].is_none() {
+                dist[idx] = Some(d + 1);
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    dist
+}
+
+/// Quita pilares (ID=3) que separan celdas libres inalcanzables del componente de `spawn`,
+/// uno a uno, hasta que todo pasillo sea alcanzable. Devuelve cuántos pilares se quitaron.
+fn reconnect_pockets(g: &mut [Vec<u8>], spawn: (i32, i32)) -> usize {
+    let h = g.len();
+    let w = g[0].len();
+    let mut removed = 0;
+    loop {
+        let reach = bfs_distances(g, spawn, |id| id == 0);
+        let reachable = |x: usize, y: usize| reach[y * w + x].is_some();
+        let unreachable_free = |g: &[Vec<u8>], x: usize, y: usize| g[y][x] == 0 && !reachable(x, y);
+
+        let mut bridge = None;
+        'search: for y in 1..h - 1 {
+            for x in 1..w - 1 {
+                if g[y][x] != 3 { continue; }
+                let nbrs = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)];
+                let touches_reach = nbrs.iter().any(|&(nx, ny)| reachable(nx, ny));
+                let touches_pocket = nbrs.iter().any(|&(nx, ny)| unreachable_free(g, nx, ny));
+                if touches_reach && touches_pocket { bridge = Some((x, y)); break 'search; }
+            }
+        }
+
+        match bridge {
+            Some((x, y)) => { g[y][x] = 0; removed += 1; }
+            None => {
+                let left = (0..h).flat_map(|y| (0..w).map(move |x| (x, y)))
+                    .filter(|&(x, y)| unreachable_free(g, x, y))
+                    .count();
+                if left > 0 { eprintln!("map: {} celdas libres siguen inalcanzables", left); }
+                return removed;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Celda libre más lejana en línea recta desde (1,1) (criterio anterior, sin jitter).
+    fn euclidean_farthest(map: &Map) -> (i32, i32) {
+        let mut best = (1, 1);
+        let mut best_d2 = -1i64;
+        for y in 1..(HEIGHT as i32 - 1) {
+            for x in 1..(WIDTH as i32 - 1) {
+                if !map.is_free(x, y) { continue; }
+                let d2 = ((x - 1) as i64).pow(2) + ((y - 1) as i64).pow(2);
+                if d2 > best_d2 { best_d2 = d2; best = (x, y); }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn distance_field_starts_at_zero_and_skips_walls() {
+        let map = Map::new_with_seed(0);
+        let field = map.distance_field((1, 1));
+        assert_eq!(field[WIDTH + 1], Some(0));
+        assert_eq!(field[0], None); // perímetro
+        for y in 0..HEIGHT as i32 {
+            for x in 0..WIDTH as i32 {
+                if map.is_wall(x, y) { assert_eq!(field[y as usize * WIDTH + x as usize], None); }
+            }
+        }
+    }
+
+    #[test]
+    fn objective_maximizes_corridor_distance_not_euclidean() {
+        let map = Map::new_with_seed(1);
+        let field = map.distance_field((1, 1));
+        let at = |(x, y): (i32, i32)| field[y as usize * WIDTH + x as usize];
+
+        let obj = map.objective_cell();
+        let euclid = euclidean_farthest(&map);
+        assert_ne!(obj, euclid, "en esta semilla ambos criterios deberían diferir");
+
+        let max = field.iter().flatten().copied().max().unwrap();
+        assert_eq!(at(obj), Some(max));
+        assert!(at(obj).unwrap() > at(euclid).unwrap());
+    }
+
+    #[test]
+    fn ascii_level_door_blocks_objective() {
+        let map = Map::from_ascii(include_str!("../assets/levels/llave.txt")).unwrap();
+        let field = map.distance_field(map.spawn);
+        let at = |(x, y): (i32, i32)| field[y as usize * map.width() + x as usize];
+
+        assert!(at(map.key_cell().unwrap()).is_some(), "la llave debe alcanzarse sin puerta");
+        assert_eq!(at(map.objective_cell()), None, "el objetivo queda tras la puerta");
+        assert!(Map::from_ascii("###\n#.\n").is_err());
+    }
+
+    #[test]
+    fn cells_and_rows_match_cell_id() {
+        let map = Map::new_with_seed(0);
+        let walls = map.cells().filter(|&(_, id)| id > 0).count();
+        assert!(walls > 0 && walls < map.width() * map.height());
+        assert_eq!(map.cells().count(), WIDTH * HEIGHT);
+        assert!(map.cells().all(|((x, y), id)| map.cell_id(x, y) == Some(id)));
+
+        let row_walls: usize = (0..HEIGHT as i32)
+            .map(|y| map.row(y).unwrap().iter().filter(|&&id| id > 0).count())
+            .sum();
+        assert_eq!(row_walls, walls);
+        assert!(map.row(-1).is_none() && map.row(HEIGHT as i32).is_none());
+    }
+
+    #[test]
+    fn ascii_roundtrip_keeps_grid_and_markers() {
+        let text = include_str!("../assets/levels/llave.txt");
+        let map = Map::from_ascii(text).unwrap();
+        assert_eq!(map.to_ascii(), text);
+
+        let mut edited = Map::new_with_seed(2);
+        edited.set_cell(5, 5, 4);
+        edited.set_key(3, 1);
+        let back = Map::from_ascii(&edited.to_ascii()).unwrap();
+        assert!(back.cells().eq(edited.cells()));
+        assert_eq!(back.key_cell(), Some((3, 1)));
+        assert_eq!(back.objective_cell(), edited.objective_cell());
+    }
+
+    #[test]
+    fn set_cell_writes_in_bounds_and_rejects_outside() {
+        let mut map = Map::from_ascii("#####\n#S..#\n#####\n").unwrap();
+        assert!(map.set_cell(2, 1, 4));
+        assert_eq!(map.cell_id(2, 1), Some(4));
+        assert!(map.set_cell(2, 1, 0));
+        assert!(map.is_free(2, 1));
+
+        for (x, y) in [(-1, 1), (5, 1), (2, -1), (2, 3)] {
+            assert!(!map.set_cell(x, y, 2), "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn set_cell_keeps_perimeter_closed() {
+        let mut map = Map::from_ascii("#####\n#S..#\n#####\n").unwrap();
+        for (x, y) in [(0, 1), (4, 1), (2, 0), (2, 2)] {
+            assert!(!map.set_cell(x, y, 0), "({}, {})", x, y);
+            assert!(!map.set_spawn(x, y));
+            assert!(map.is_wall(x, y));
+        }
+        // Una puerta en el borde también abriría el mapa al tener la llave
+        for (x, y) in [(0, 1), (4, 1), (2, 0), (2, 2)] {
+            assert!(!map.set_cell(x, y, DOOR_ID), "({}, {})", x, y);
+            assert!(map.is_wall(x, y) && !map.is_door(x, y));
+        }
+        // Cambiar el tipo de muro del borde sí está permitido
+        assert!(map.set_cell(0, 1, 3));
+        assert_eq!(map.cell_id(0, 1), Some(3));
+
+        // Un nivel que ya viene abierto no se acepta para jugar (el editor sí lo abre)
+        for open in ["....\n.S..\n....\n", "#####\n#S..D\n#####\n"] {
+            let err = Map::from_ascii(open).err().unwrap();
+            assert!(err.contains("borde"), "{}", err);
+            assert!(Map::parse_ascii(open).is_ok());
+        }
+    }
+
+    #[test]
+    fn set_cell_does_not_bury_markers() {
+        let mut map = Map::from_ascii(include_str!("../assets/levels/llave.txt")).unwrap();
+        let markers = [map.spawn_cell(), map.objective_cell(), map.key_cell().unwrap()];
+        for (x, y) in markers {
+            assert!(!map.set_cell(x, y, 2), "({}, {})", x, y);
+            assert!(map.is_free(x, y));
+        }
+        assert_eq!(Map::from_ascii(&map.to_ascii()).unwrap().to_ascii(), map.to_ascii());
+    }
+
+    #[test]
+    fn editor_parse_keeps_unplayable_levels() {
+        let text = "#####\n#S#K#\n#####\n";
+        assert!(Map::from_ascii(text).is_err());
+        let map = Map::parse_ascii(text).unwrap();
+        assert!(map.validate().is_err());
+        assert_eq!(map.to_ascii(), text);
+    }
+
+    #[test]
+    fn set_cell_invalidates_cached_objective() {
+        let mut map = Map::from_ascii("######\n#S...#\n######\n").unwrap();
+        assert_eq!(map.objective_cell(), (4, 1));
+        assert!(map.set_cell(3, 1, 2));
+        assert_eq!(map.objective_cell(), (2, 1));
+    }
+
+    #[test]
+    fn every_free_cell_is_reachable_after_pillar_pass() {
+        for seed in 0..16 {
+            let map = Map::new_with_seed(seed);
+            let field = map.distance_field((1, 1));
+            for y in 0..HEIGHT as i32 {
+                for x in 0..WIDTH as i32 {
+                    if map.is_free(x, y) {
+                        assert!(field[y as usize * WIDTH + x as usize].is_some(), "seed {}: ({}, {}) aislada", seed, x, y);
+                    }
+                }
+            }
+            let (ox, oy) = map.objective_cell();
+            assert!(field[oy as usize * WIDTH + ox as usize].is_some(), "seed {}: objetivo aislado", seed);
+        }
+    }
+
+    #[test]
+    fn pillar_sealing_a_pocket_is_removed() {
+        // (3,1) queda encerrada por el pilar en (2,1)
+        let mut g: Vec<Vec<u8>> = ["#####", "#.3.#", "#####"]
+            .iter()
+            .map(|r| r.bytes().map(|b| match b { b'#' => 1, b'3' => 3, _ => 0 }).collect())
+            .collect();
+        assert_eq!(reconnect_pockets(&mut g, (1, 1)), 1);
+        assert_eq!(g[1][2], 0);
+    }
+
+    #[test]
+    fn ascii_level_rejects_bad_default_spawn() {
+        // Sin `S` el spawn es (1, 1): fuera del mapa o dentro de un muro es un error
+        assert!(Map::from_ascii("#\n").is_err());
+        let err = Map::from_ascii("####\n##K#\n####\n").err().unwrap();
+        assert!(err.contains("spawn"), "{}", err);
+    }
+
+    #[test]
+    fn ascii_level_rejects_unreachable_key() {
+        assert!(Map::from_ascii("#####\n#S#K#\n#####\n").is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::render::neon_from_phase;
+
+/// Tope de partículas vivas; las ráfagas nuevas desplazan a las más antiguas.
+pub const MAX_PARTICLES: usize = 256;
+/// Vida de cada partícula en segundos (se desvanece linealmente).
+pub const PARTICLE_LIFE: f32 = 0.5;
+/// Gravedad sobre la altura `z` (px/s²).
+const GRAVITY: f32 = 220.0;
+/// Rozamiento horizontal (1/s).
+const DRAG: f32 = 2.5;
+
+/// Partícula en coordenadas de mundo: `pos = (x, y, z)` en píxeles, con `z` la altura
+/// relativa a los ojos del jugador (0 = horizonte, -TILE_SIZE/2 = suelo).
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub pos: (f32, f32, f32),
+    pub vel: (f32, f32, f32),
+    /// Segundos restantes (`<= 0` = muerta).
+    pub life: f32,
+    pub color: u32,
+}
+
+impl Particle {
+    /// Opacidad en [0, 1] según la vida restante.
+    pub fn alpha(&self) -> f32 {
+        (self.life / PARTICLE_LIFE).clamp(0.0, 1.0)
+    }
+}
+
+/// Conjunto acotado de partículas con su propio xorshift (determinista).
+pub struct ParticlePool {
+    particles: Vec<Particle>,
+    rng: u32,
+}
+
+impl ParticlePool {
+    pub fn new() -> Self {
+        Self { particles: Vec::with_capacity(MAX_PARTICLES), rng: 0x2545_F491 }
+    }
+
+    /// Ráfaga de `count` partículas neón desde (x, y, z) en todas direcciones.
+    /// `anim_t` desplaza la paleta para que cada ráfaga tenga su tono.
+    pub fn burst(&mut self, x: f32, y: f32, z: f32, count: usize, anim_t: f32) {
+        let count = count.min(MAX_PARTICLES);
+        let excess = (self.particles.len() + count).saturating_sub(MAX_PARTICLES);
+        self.particles.drain(..excess);
+        for i in 0..count {
+            let ang = self.next_unit() * std::f32::consts::TAU;
+            let speed = 40.0 + self.next_unit() * 110.0;
+            let vz = 30.0 + self.next_unit() * 120.0;
+            let life = PARTICLE_LIFE * (0.7 + 0.3 * self.next_unit());
+            self.particles.push(Particle {
+                pos: (x, y, z),
+                vel: (ang.cos() * speed, ang.sin() * speed, vz),
+                life,
+                color: neon_from_phase(anim_t * 0.6 + i as f32 * 0.35),
+            });
+        }
+    }
+
+    /// Avanza la simulación `dt` segundos y descarta las partículas muertas.
+    /// `floor_z` es la altura del suelo: ahí las partículas dejan de caer.
+    pub fn update(&mut self, dt: f32, floor_z: f32) {
+        let drag = (-DRAG * dt).exp();
+        for p in &mut self.particles {
+            p.vel.0 *= drag;
+            p.vel.1 *= drag;
+            p.vel.2 -= GRAVITY * dt;
+            p.pos.0 += p.vel.0 * dt;
+            p.pos.1 += p.vel.1 * dt;
+            p.pos.2 += p.vel.2 * dt;
+            if p.pos.2 < floor_z { p.pos.2 = floor_z; p.vel.2 = 0.0; }
+            p.life -= dt;
+        }
+        self.particles.retain(|p| p.life > 0.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /// Número pseudoaleatorio en [0, 1).
+    fn next_unit(&mut self) -> f32 {
+        self.rng ^= self.rng << 13; self.rng ^= self.rng >> 17; self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_is_capped_and_keeps_newest() {
+        let mut pool = ParticlePool::new();
+        pool.burst(0.0, 0.0, 0.0, MAX_PARTICLES, 0.0);
+        pool.burst(100.0, 100.0, 0.0, 10, 0.0);
+        assert_eq!(pool.particles().len(), MAX_PARTICLES);
+        let newest = &pool.particles()[MAX_PARTICLES - 10..];
+        assert!(newest.iter().all(|p| p.pos == (100.0, 100.0, 0.0)));
+    }
+
+    #[test]
+    fn particles_fade_and_expire() {
+        let mut pool = ParticlePool::new();
+        pool.burst(0.0, 0.0, 0.0, 16, 0.0);
+        pool.update(PARTICLE_LIFE * 0.5, -20.0);
+        assert!(pool.particles().iter().all(|p| p.alpha() < 1.0 && p.pos.2 >= -20.0));
+        for _ in 0..20 { pool.update(0.05, -20.0); }
+        assert!(pool.particles().is_empty());
+    }
+}
@@ -5,33 +5,87 @@ use crate::map::Map;
 pub const RADIUS_PX: f32 = 12.0;
 /// Margen pequeño para evitar vibraciones en bordes
 pub const EPSILON_PX: f32 = 0.75;
+/// Inclinación vertical máxima (desplazamiento del horizonte en unidades de plano de proyección).
+/// Con `FOV_V` el horizonte sale de pantalla a partir de ~0.43; el render centra las paredes
+/// en el horizonte sin acotar, así que todo el rango sigue moviendo la vista.
+pub const PITCH_MAX: f32 = 0.6;
+/// Campo de visión vertical fijo (rad): el de 60° horizontales a 4:3, ≈46.8°.
+pub const FOV_V: f32 = 0.817_275_7;
+/// dt máximo por cuadro (s). Con pasos mayores el jugador podría atravesar muros de una celda.
+pub const MAX_DT: f32 = 0.05;
+
+/// Acota el delta de tiempo a `[0, MAX_DT]` antes de usarlo en movimiento, animación y temporizadores.
+pub fn clamp_dt(dt: f32) -> f32 {
+    dt.clamp(0.0, MAX_DT)
+}
+
+/// FOV horizontal que corresponde a `FOV_V` en una pantalla `screen_w`×`screen_h`.
+pub fn fov_for_screen(screen_w: usize, screen_h: usize) -> f32 {
+    let aspect = screen_w as f32 / screen_h.max(1) as f32;
+    2.0 * ((FOV_V * 0.5).tan() * aspect).atan()
+}
+
+/// Rangos válidos de velocidad (evitan valores degenerados desde la configuración)
+pub const MOVE_SPEED_RANGE: (f32, f32) = (20.0, 600.0);
+pub const ROT_SPEED_RANGE: (f32, f32) = (0.5, 8.0);
+
+/// Velocidades ajustables del jugador (settings × dificultad).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerConfig {
+    pub move_speed: f32, // px/seg
+    pub rot_speed: f32,  // rad/seg
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self { move_speed: 160.0, rot_speed: 2.6 }
+    }
+}
+
+impl PlayerConfig {
+    /// Acota las velocidades a `MOVE_SPEED_RANGE`/`ROT_SPEED_RANGE`; valores no finitos usan el defecto.
+    pub fn clamped(self) -> Self {
+        let fix = |v: f32, (lo, hi): (f32, f32), def: f32| if v.is_finite() { v.clamp(lo, hi) } else { def };
+        let def = Self::default();
+        Self {
+            move_speed: fix(self.move_speed, MOVE_SPEED_RANGE, def.move_speed),
+            rot_speed: fix(self.rot_speed, ROT_SPEED_RANGE, def.rot_speed),
+        }
+    }
+}
 
 /// representa al jugador en el mundo.
 pub struct Player {
     pub x: f32,       // posición X en mundo 
     pub y: f32,       // posición Y en mundo 
     pub angle: f32,   // orientación en radianes
+    pub pitch: f32,   // inclinación vertical en [-PITCH_MAX, PITCH_MAX] (>0 = mirar arriba)
     pub move_speed: f32, // px/seg
     pub rot_speed: f32,  // rad/seg
-    pub fov: f32,        // campo de visión 
+    pub fov: f32,        // campo de visión horizontal (ver `fit_screen`)
+    pub has_key: bool,   // abre las puertas (DOOR_ID)
 }
 
 impl Player {
-    /// Crea un jugador en (x, y). Ángulo inicial mirando hacia +X (0 rad).
-    pub fn new(x: f32, y: f32) -> Self {
+    /// Crea un jugador en (x, y) con velocidades de `config` (acotadas).
+    /// Ángulo inicial mirando hacia +X (0 rad).
+    pub fn with_config(x: f32, y: f32, config: PlayerConfig) -> Self {
+        let config = config.clamped();
         Self {
             x,
             y,
             angle: 0.0,
-            move_speed: 160.0, // ajustable
-            rot_speed: 2.6,    // ajustable 
+            pitch: 0.0,
+            move_speed: config.move_speed,
+            rot_speed: config.rot_speed,
             fov: FRAC_PI_3,    
+            has_key: false,
         }
     }
 
-    pub fn from_map_spawn(map: &crate::map::Map) -> Self {
+    pub fn from_map_spawn(map: &crate::map::Map, config: PlayerConfig) -> Self {
         let (sx, sy) = map.recommended_spawn();
-        Self::new(sx, sy)
+        Self::with_config(sx, sy, config)
     }
 
     /// Vector dirección normalizado (cos(angle), sin(angle)).
@@ -59,6 +113,22 @@ impl Player {
 
 
 
+    /// Gira `delta` radianes (positivo = derecha).
+    pub fn rotate(&mut self, delta: f32) {
+        self.angle += delta;
+        self.normalize_angle();
+    }
+
+    /// Ajusta el FOV horizontal al aspecto de la pantalla (el vertical queda en `FOV_V`).
+    pub fn fit_screen(&mut self, screen_w: usize, screen_h: usize) {
+        self.fov = fov_for_screen(screen_w, screen_h);
+    }
+
+    /// Inclina la vista `delta` (positivo = arriba), acotado a ±`PITCH_MAX`.
+    pub fn look_vertical(&mut self, delta: f32) {
+        self.pitch = (self.pitch + delta).clamp(-PITCH_MAX, PITCH_MAX);
+    }
+
     /// Avanzar hacia adelante 
     pub fn forward(&mut self, dt: f32) {
         let (dx, dy) = self.dir();
@@ -141,7 +211,7 @@ impl Player {
         for (px, py) in samples.iter() {
             let (cx, cy) = map.world_to_cell(*px, *py);
             if !map.in_bounds(cx, cy) { return true; } // fuera = pared
-            if map.is_wall(cx, cy) { return true; }
+            if map.is_wall(cx, cy) && !(self.has_key && map.is_door(cx, cy)) { return true; }
         }
         false
     }
@@ -154,4 +224,43 @@ impl Player {
         while a < -PI { a += 2.0 * PI; }
         self.angle = a;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pared de una celda (x = 120..160) entre el spawn y otro pasillo
+    const THIN_WALL: &str = "#######\n#S.#..#\n#######\n";
+
+    #[test]
+    fn clamped_dt_near_wall_is_bounded() {
+        let map = Map::from_ascii(THIN_WALL).unwrap();
+        let mut p = Player::from_map_spawn(&map, PlayerConfig::default());
+        assert_eq!(clamp_dt(0.9), MAX_DT);
+        assert_eq!(clamp_dt(-1.0), 0.0);
+
+        for _ in 0..100 {
+            let x0 = p.x;
+            p.forward_collide(clamp_dt(0.9), &map);
+            assert!(p.x - x0 <= p.move_speed * MAX_DT + 1e-3);
+            assert!(p.x + RADIUS_PX < 120.0, "el jugador nunca entra ni cruza la pared");
+        }
+    }
+
+    #[test]
+    fn fov_v_matches_60_degrees_at_4_3() {
+        assert!((fov_for_screen(800, 600) - FRAC_PI_3).abs() < 1e-5);
+        assert!(fov_for_screen(1920, 1080) > FRAC_PI_3);
+    }
+
+    #[test]
+    fn config_speeds_are_clamped() {
+        let p = Player::with_config(0.0, 0.0, PlayerConfig { move_speed: -5.0, rot_speed: f32::NAN });
+        assert_eq!(p.move_speed, MOVE_SPEED_RANGE.0);
+        assert_eq!(p.rot_speed, PlayerConfig::default().rot_speed);
+
+        let p = Player::with_config(0.0, 0.0, PlayerConfig { move_speed: 200.0, rot_speed: 3.0 });
+        assert_eq!((p.move_speed, p.rot_speed), (200.0, 3.0));
+    }
+}
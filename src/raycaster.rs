@@ -1,4 +1,4 @@
-use crate::map::{Map, TILE_SIZE, WIDTH as MAP_W, HEIGHT as MAP_H};
+use crate::map::{Map, TILE_SIZE, DOOR_ID};
 use crate::player::Player;
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -8,21 +8,47 @@ pub struct RayHit {
     /// ID de pared (0 si no se encontró; en mapa cerrado siempre > 0).
     pub wall_id: u8,
     pub hit_vertical: bool,
+    /// Celda del muro alcanzado.
+    pub cell: (i32, i32),
 }
 
-/// Lanza todos los rayos necesarios para el ancho de la pantalla.
-pub fn cast_all_rays(map: &Map, player: &Player, screen_w: usize) -> Vec<RayHit> {
-    let mut hits = vec![RayHit::default(); screen_w];
-    for col in 0..screen_w {
-        hits[col] = cast_ray_for_column(map, player, screen_w, col);
+/// Lanza un rayo cada `ray_step` columnas (más la última columna).
+/// La muestra `i` corresponde a la columna `sample_column(i, screen_w, ray_step)`;
+/// con `ray_step = 1` hay exactamente un rayo por columna.
+pub fn cast_all_rays(map: &Map, player: &Player, screen_w: usize, ray_step: usize) -> Vec<RayHit> {
+    let n = sample_count(screen_w, ray_step);
+    let mut hits = vec![RayHit::default(); n];
+    for (i, hit) in hits.iter_mut().enumerate() {
+        *hit = cast_ray_for_column(map, player, screen_w, sample_column(i, screen_w, ray_step));
     }
     hits
 }
 
+/// Número de rayos que lanza `cast_all_rays`.
+pub fn sample_count(screen_w: usize, ray_step: usize) -> usize {
+    if screen_w == 0 { return 0; }
+    (screen_w - 1).div_ceil(ray_step.max(1)) + 1
+}
+
+/// Columna de pantalla de la muestra `i`.
+pub fn sample_column(i: usize, screen_w: usize, ray_step: usize) -> usize {
+    (i * ray_step.max(1)).min(screen_w.saturating_sub(1))
+}
+
+/// Rayo central en la dirección de la mirada (`player.dir()`): primer muro delante del jugador.
+pub fn cast_forward(map: &Map, player: &Player) -> RayHit {
+    cast_ray(map, player, player.angle)
+}
+
 fn cast_ray_for_column(map: &Map, player: &Player, screen_w: usize, col: usize) -> RayHit {
-    // Ángulo del rayo dentro del FOV
+    // Ángulo del rayo: columnas equiespaciadas sobre el plano de proyección (no en ángulo),
+    // igual que `render::sprite_projection`, para que paredes y sprites compartan la focal
     let t = if screen_w > 1 { col as f32 / (screen_w as f32 - 1.0) } else { 0.5 };
-    let ray_angle = player.angle - player.fov * 0.5 + t * player.fov;
+    let offset = ((2.0 * t - 1.0) * (player.fov * 0.5).tan()).atan();
+    cast_ray(map, player, player.angle + offset)
+}
+
+fn cast_ray(map: &Map, player: &Player, ray_angle: f32) -> RayHit {
     let ray_dir_x = ray_angle.cos();
     let ray_dir_y = ray_angle.sin();
 
@@ -63,7 +89,7 @@ fn cast_ray_for_column(map: &Map, player: &Player, screen_w: usize, col: usize)
     let mut hit_vertical = false;
 
     // Límite de pasos de seguridad (mapa cerrado debe chocar antes)
-    let max_steps = (MAP_W.max(MAP_H) * 4) as usize;
+    let max_steps = map.width().max(map.height()) * 4;
     for _ in 0..max_steps {
         if side_dist_x < side_dist_y {
             side_dist_x += delta_dist_x;
@@ -79,14 +105,16 @@ fn cast_ray_for_column(map: &Map, player: &Player, screen_w: usize, col: usize)
             return RayHit::default();
         }
         if let Some(id) = map.cell_id(map_x, map_y) {
+            // Con la llave, las puertas se atraviesan
+            if id == DOOR_ID && player.has_key { continue; }
             if id > 0 { hit_id = id; break; }
         }
     }
 
     if hit_id == 0 { return RayHit::default(); }
 
-    // Distancia perpendicular en **unidades de celda**
-    let perp_cells = if hit_vertical {
+    // Distancia a lo largo del rayo (dirección unitaria) en **unidades de celda**
+    let ray_cells = if hit_vertical {
         // Cruce en X
         let denom = if ray_dir_x.abs() < 1e-6 { 1e-6 } else { ray_dir_x };
         ((map_x as f32 - pos_cell_x) + (1.0 - step_x as f32) * 0.5) / denom
@@ -96,7 +124,67 @@ fn cast_ray_for_column(map: &Map, player: &Player, screen_w: usize, col: usize)
         ((map_y as f32 - pos_cell_y) + (1.0 - step_y as f32) * 0.5) / denom
     };
 
-    let dist_px = perp_cells.abs() * TILE_SIZE as f32;
+    // Proyección sobre la dirección de la mirada: distancia perpendicular (sin ojo de pez)
+    let perp_cells = ray_cells.abs() * (ray_angle - player.angle).cos();
+    let dist_px = perp_cells * TILE_SIZE as f32;
+
+    RayHit { dist_px, wall_id: hit_id, hit_vertical, cell: (map_x, map_y) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::PlayerConfig;
+    use core::f32::consts::FRAC_PI_2;
+
+    /// Ancho impar: la columna central mira exactamente en `player.angle`.
+    const SCREEN_W: usize = 101;
+
+    fn player_at(map: &Map, angle: f32) -> Player {
+        let (x, y) = map.recommended_spawn();
+        let mut player = Player::with_config(x, y, PlayerConfig::default());
+        player.angle = angle;
+        player
+    }
+
+    #[test]
+    fn center_column_hits_wall_at_known_distance() {
+        let map = Map::from_ascii("#######\n#S...3#\n#######\n").unwrap();
+        let player = player_at(&map, 0.0);
+        let hit = cast_ray_for_column(&map, &player, SCREEN_W, SCREEN_W / 2);
+        // Centro de (1,1) = 60 px; cara izquierda de (5,1) = 200 px
+        assert!((hit.dist_px - 140.0).abs() < 1e-3, "{:?}", hit);
+        assert_eq!(hit.wall_id, 3);
+        assert!(hit.hit_vertical);
+        assert_eq!(hit.cell, (5, 1));
+    }
+
+    #[test]
+    fn open_corridor_reaches_far_perimeter() {
+        let map = Map::from_ascii("###\n#S#\n#.#\n#.#\n###\n").unwrap();
+        let player = player_at(&map, FRAC_PI_2);
+        let hit = cast_ray_for_column(&map, &player, SCREEN_W, SCREEN_W / 2);
+        // Centro de (1,1) = 60 px; perímetro inferior empieza en y = 160 px
+        assert!((hit.dist_px - 100.0).abs() < 1e-3, "{:?}", hit);
+        assert_eq!(hit.wall_id, 1);
+        assert!(!hit.hit_vertical);
+        assert_eq!(hit.cell, (1, 4));
+    }
 
-    RayHit { dist_px, wall_id: hit_id, hit_vertical }
+    #[test]
+    fn off_center_columns_use_perpendicular_distance() {
+        // Pared plana en x = 5: sin ojo de pez, todas las columnas miden lo mismo
+        let map = Map::from_ascii("#######\n#....2#\n#....2#\n#S...2#\n#....2#\n#....2#\n#######\n").unwrap();
+        let player = player_at(&map, 0.0);
+        for col in [0, SCREEN_W / 4, SCREEN_W / 2, 3 * SCREEN_W / 4, SCREEN_W - 1] {
+            let hit = cast_ray_for_column(&map, &player, SCREEN_W, col);
+            assert!((hit.dist_px - 140.0).abs() < 1e-2, "columna {}: {:?}", col, hit);
+            assert_eq!(hit.wall_id, 2, "columna {}", col);
+            assert!(hit.hit_vertical, "columna {}", col);
+            assert_eq!(hit.cell.0, 5, "columna {}", col);
+        }
+        // Los extremos del FOV (±30°) caen dos celdas arriba/abajo del jugador
+        assert_eq!(cast_ray_for_column(&map, &player, SCREEN_W, 0).cell, (5, 1));
+        assert_eq!(cast_ray_for_column(&map, &player, SCREEN_W, SCREEN_W - 1).cell, (5, 5));
+    }
 }
@@ -1,15 +1,19 @@
-use crate::map::{Map, TILE_SIZE};
-use crate::player::Player;
+use crate::map::{Map, TILE_SIZE, DOOR_ID};
+use crate::particles::Particle;
+use crate::player::{Player, FOV_V, fov_for_screen};
 use crate::raycaster::{self, RayHit};
-use std::f32::consts::PI;
+use std::f32::consts::{FRAC_PI_2, PI};
 
 const SKY: u32 = 0x00D5FF;   // cyan eléctrico
 const FLOOR: u32 = 0x1E1B2E; // púrpura muy oscuro
 const OBJ_COLOR: u32 = 0xFF2ED1; // magenta brillante del objetivo (sprite 3D)
+const KEY_COLOR: u32 = 0xFFD700; // dorado de la llave (sprite 3D)
+const DOOR_COLOR: u32 = 0x8B5A2B; // madera de la puerta (sin animar)
 
 // Colores por ID de pared (ajustables luego)
 fn wall_color(id: u8) -> u32 {
     match id {
+        DOOR_ID => DOOR_COLOR,
         1 => 0xFF6EC7, // rosa intenso
         2 => 0xFFA500, // naranja vivo
         3 => 0x00FF88, // verde neón
@@ -29,7 +33,7 @@ fn shade(color: u32, factor: f32) -> u32 {
 }
 
 // ====== NEÓN ANIMADO (helpers a nivel de módulo) ======
-fn neon_from_phase(phase: f32) -> u32 {
+pub fn neon_from_phase(phase: f32) -> u32 {
     // Paleta neón animada con senoides desfasadas 120°
     let base = 0.35; // brillo mínimo
     let amp  = 0.65; // amplitud
@@ -60,6 +64,9 @@ const MM_WALL: u32 = 0xFAFAFA;    // paredes más contrastadas
 const MM_PLAYER: u32 = 0x00FFFF;  // cian neón (igual)
 const MM_BORDER: u32 = 0x606060;  // borde un poco más claro
 const MM_OBJECTIVE: u32 = 0xFF00FF;  // objetivo magenta vivo
+const MM_DOOR: u32 = 0xC08040;       // puerta cerrada
+const MM_DOOR_OPEN: u32 = 0x403020;  // puerta abierta (con llave)
+const MM_KEY: u32 = 0xFFD700;        // llave
 
 #[inline]
 fn draw_rect(buffer: &mut [u32], w: usize, h: usize, x: usize, y: usize, rw: usize, rh: usize, color: u32) {
@@ -73,6 +80,20 @@ fn draw_rect(buffer: &mut [u32], w: usize, h: usize, x: usize, y: usize, rw: usi
     }
 }
 
+/// Mezcla `color` sobre el rectángulo `(x, y, ancho, alto)` con opacidad `alpha` en [0, 1].
+pub fn blend_rect(buffer: &mut [u32], w: usize, h: usize, rect: (usize, usize, usize, usize), color: u32, alpha: f32) {
+    let (x, y, rw, rh) = rect;
+    let alpha = alpha.clamp(0.0, 1.0);
+    let x2 = (x + rw).min(w);
+    let y2 = (y + rh).min(h);
+    for yy in y..y2 {
+        let row = yy * w;
+        for xx in x..x2 {
+            buffer[row + xx] = lerp_color(buffer[row + xx], color, alpha);
+        }
+    }
+}
+
 #[inline]
 fn draw_line(buffer: &mut [u32], w: usize, h: usize, x0: i32, y0: i32, x1: i32, y1: i32, color: u32) {
     // Bresenham sencillo
@@ -106,6 +127,7 @@ const TEXT_SHADOW: u32 = 0x060606;
 fn glyph5x7(ch: char) -> [u8; 5] {
     match ch {
         'A' => [0b01110, 0b10001, 0b11111, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b11110, 0b10001, 0b11110],
         'C' => [0b01110, 0b10001, 0b10000, 0b10001, 0b01110],
         'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b11110],
         'E' => [0b11111, 0b10000, 0b11110, 0b10000, 0b11111],
@@ -113,6 +135,7 @@ fn glyph5x7(ch: char) -> [u8; 5] {
         'G' => [0b01110, 0b10000, 0b10111, 0b10001, 0b01110],
         'I' => [0b11111, 0b00100, 0b00100, 0b00100, 0b11111],
         'J' => [0b00111, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10001, 0b10010, 0b11100, 0b10010, 0b10001],
         'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
         'M' => [0b10001, 0b11011, 0b10101, 0b10001, 0b10001],
         'N' => [0b10001, 0b11001, 0b10101, 0b10011, 0b10001],
@@ -132,6 +155,7 @@ fn glyph5x7(ch: char) -> [u8; 5] {
         '9' => [0b01110, 0b10001, 0b01111, 0b00001, 0b01110],
         'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100],
         'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
         'Y' => [0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
         'W' => [0b10001, 0b10001, 0b10101, 0b10101, 0b01010],
         '!' => [0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
@@ -176,8 +200,32 @@ fn draw_text_centered5x7(buffer: &mut [u32], w: usize, h: usize, cx: usize, y: u
     draw_text5x7(buffer, w, h, x, y, text, scale, color);
 }
 
-/// Dibuja un minimapa en la esquina superior izquierda.
-pub fn draw_minimap(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Map, player: &Player, obj_x: f32, obj_y: f32, anim_t: f32) {
+/// Zona de pantalla para un grid: origen (ox, oy), `scale` px por celda y cuántas celdas dibujar.
+#[derive(Clone, Copy)]
+struct GridView {
+    ox: usize,
+    oy: usize,
+    scale: usize,
+    cells_x: usize,
+    cells_y: usize,
+}
+
+/// Dibuja las paredes de las primeras `cells_x × cells_y` celdas como bloques de `scale` px a partir
+/// de (ox, oy). `color_of(cx, cy, id)` elige el color de cada pared; los pasillos no se dibujan.
+fn draw_grid_cells(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Map, view: GridView, color_of: impl Fn(usize, usize, u8) -> u32) {
+    let GridView { ox, oy, scale, cells_x, cells_y } = view;
+    for cy in 0..cells_y {
+        let Some(row) = map.row(cy as i32) else { break; };
+        for (cx, &id) in row.iter().enumerate().take(cells_x) {
+            if id == 0 { continue; }
+            draw_block(buffer, screen_w, screen_h, ox + cx * scale, oy + cy * scale, scale, color_of(cx, cy, id));
+        }
+    }
+}
+
+/// Dibuja un minimapa de `scene` en la esquina superior izquierda.
+pub fn draw_minimap(buffer: &mut [u32], screen_w: usize, screen_h: usize, scene: &Scene) {
+    let &Scene { map, player, objective: (obj_x, obj_y), key, anim_t, .. } = scene;
     // Tamaño máximo del minimapa (no más de ~1/3 del ancho ni 1/3 del alto)
     let max_w = screen_w / 3;
     let max_h = screen_h / 3;
@@ -213,18 +261,16 @@ pub fn draw_minimap(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &
     let cells_x = (mm_w / scale).min(map.width());
     let cells_y = (mm_h / scale).min(map.height());
 
-    for cy in 0..cells_y {
-        for cx in 0..cells_x {
-            if map.is_wall(cx as i32, cy as i32) {
-                let x = margin + cx * scale;
-                let y = margin + cy * scale;
-                // Fase por celda para variedad visual sin leer el ID
-                let phase = anim_t * 0.9 + (cx as f32) * 0.25 + (cy as f32) * 0.17;
-                let col = neon_from_phase(phase);
-                draw_block(buffer, screen_w, screen_h, x, y, scale, col);
-            }
+    let view = GridView { ox: margin, oy: margin, scale, cells_x, cells_y };
+    draw_grid_cells(buffer, screen_w, screen_h, map, view, |cx, cy, id| {
+        if id == DOOR_ID {
+            if player.has_key { MM_DOOR_OPEN } else { MM_DOOR }
+        } else {
+            // Fase por celda para variedad visual sin leer el ID
+            let phase = anim_t * 0.9 + (cx as f32) * 0.25 + (cy as f32) * 0.17;
+            neon_from_phase(phase)
         }
-    }
+    });
 
     // Jugador: convertir mundo -> celda -> minimapa
     let (pcx_f, pcy_f) = {
@@ -263,14 +309,255 @@ pub fn draw_minimap(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &
         let my = oy.saturating_sub(ms / 2);
         draw_rect(buffer, screen_w, screen_h, mx, my, ms, ms, MM_OBJECTIVE);
     }
+
+    // Llave (si sigue en el mapa)
+    if let Some((kx, ky)) = key {
+        let kcx = (kx / ts2) as usize;
+        let kcy = (ky / ts2) as usize;
+        if kcx < cells_x && kcy < cells_y {
+            let ms: usize = if scale >= 3 { 3 } else { 2 };
+            let mx = (margin + kcx * scale).saturating_sub(ms / 2);
+            let my = (margin + kcy * scale).saturating_sub(ms / 2);
+            draw_rect(buffer, screen_w, screen_h, mx, my, ms, ms, MM_KEY);
+        }
+    }
+}
+
+/// Distancias al plano de proyección en píxeles: `(horizontal, vertical)`.
+/// El FOV vertical es fijo (`FOV_V`) y el horizontal se deriva del aspecto (`fov_for_screen`,
+/// el mismo que usa el jugador), así que ambas coinciden: una sola focal para los dos ejes.
+pub fn projection_planes(screen_w: usize, screen_h: usize) -> (f32, f32) {
+    let horiz = (screen_w as f32 / 2.0) / (fov_for_screen(screen_w, screen_h) * 0.5).tan();
+    let vert = (screen_h as f32 / 2.0) / (FOV_V * 0.5).tan();
+    (horiz, vert)
+}
+
+/// Altura proyectada (sin recortar) en píxeles de una pared a `dist_px`.
+pub fn wall_column_height(dist_px: f32, screen_w: usize, screen_h: usize) -> f32 {
+    let (_, proj_v) = projection_planes(screen_w, screen_h);
+    TILE_SIZE as f32 * proj_v / dist_px
+}
+
+/// Proyecta un punto del mundo a pantalla: devuelve `(screen_x, dist, rel)` con `rel` el ángulo
+/// relativo al jugador en [-PI, PI] y `dist` la profundidad perpendicular (comparable con
+/// `RayHit::dist_px`). `None` si el punto está encima del jugador.
+fn sprite_projection(player: &Player, screen_w: usize, proj_plane: f32, wx: f32, wy: f32) -> Option<(f32, f32, f32)> {
+    let dx = wx - player.x;
+    let dy = wy - player.y;
+    let dist = (dx * dx + dy * dy).sqrt();
+    if !dist.is_finite() || dist <= 1.0 { return None; }
+    let mut rel = dy.atan2(dx) - player.angle;
+    while rel > PI { rel -= 2.0 * PI; }
+    while rel < -PI { rel += 2.0 * PI; }
+    let screen_x = (screen_w as f32) * 0.5 + rel.tan() * proj_plane;
+    Some((screen_x, dist * rel.cos(), rel))
+}
+
+/// Si el ángulo relativo `rel` cae en el cono donde se dibujan sprites: el FOV con margen,
+/// pero siempre por delante del jugador (con aspectos muy anchos `0.6 * fov` supera PI/2).
+fn sprite_in_view(player: &Player, rel: f32) -> bool {
+    rel.abs() <= (player.fov * 0.6).min(FRAC_PI_2 * 0.98)
+}
+
+/// Tamaño de una partícula en el mundo (px).
+const PARTICLE_SIZE_PX: f32 = 4.0;
+
+/// Cámara de un fotograma: jugador, altura del horizonte y focales de `projection_planes`.
+#[derive(Clone, Copy)]
+struct CameraView<'a> {
+    player: &'a Player,
+    horizon: f32,
+    proj_plane: f32,
+    proj_v: f32,
+}
+
+/// Partículas como cuadrados siempre de frente a la cámara; `z` se proyecta respecto al horizonte.
+fn draw_particles(buffer: &mut [u32], screen_w: usize, screen_h: usize, camera: CameraView, hits: &[RayHit], particles: &[Particle]) {
+    let CameraView { player, horizon, proj_plane, proj_v } = camera;
+    for p in particles {
+        let Some((screen_x, dist, rel)) = sprite_projection(player, screen_w, proj_plane, p.pos.0, p.pos.1) else { continue; };
+        if !sprite_in_view(player, rel) { continue; }
+        let scale = proj_v / dist;
+        let size = (PARTICLE_SIZE_PX * scale).clamp(1.0, 12.0);
+        let cy = horizon - p.pos.2 * scale;
+        let x0 = (screen_x - size * 0.5).round() as i32;
+        let y0 = (cy - size * 0.5).round().max(0.0) as i32;
+        let y1 = ((cy + size * 0.5).round() as i32).min(screen_h as i32 - 1);
+        let alpha = p.alpha();
+        for sx in x0.max(0)..(x0 + size as i32).min(screen_w as i32) {
+            if dist > hits[sx as usize].dist_px - 0.5 { continue; }
+            for sy in y0..=y1 {
+                let idx = sy as usize * screen_w + sx as usize;
+                buffer[idx] = lerp_color(buffer[idx], p.color, alpha);
+            }
+        }
+    }
+}
+
+/// Cubo proyectado: columna central `screen_x`, altura del horizonte `center_y`,
+/// profundidad `dist` y tamaño `base` en píxeles.
+#[derive(Clone, Copy)]
+struct CubeSprite {
+    screen_x: f32,
+    center_y: f32,
+    dist: f32,
+    base: f32,
+    color: u32,
+}
+
+/// Cubo “flotante” con test de profundidad por columna. Devuelve true si se dibujó alguna columna.
+fn draw_cube_sprite(buffer: &mut [u32], screen_w: usize, screen_h: usize, hits: &[RayHit], cube: CubeSprite) -> bool {
+    let CubeSprite { screen_x, center_y, dist, base, color } = cube;
+    let mut drew_any = false;
+
+    let cube = (base * 0.9).max(6.0);       // ancho del cubo
+    let front_h = (cube * 0.7).max(3.0);    // alto del frente
+    let top_h = (cube * 0.28).max(2.0);     // alto de la tapa
+    let half_w = (cube * 0.5).max(2.0);
+
+    let left = (screen_x - half_w).floor() as i32;
+    let right = (screen_x + half_w).ceil() as i32;
+
+    // elevación leve para simular que flota
+    let lift = (cube * 0.18) as f32;
+    let front_top_f = center_y - front_h * 0.5 - lift;
+    let front_bot_f = center_y + front_h * 0.5 - lift;
+    let top_top_f = front_top_f - top_h;
+    let top_bot_f = front_top_f;
+
+    let front_top = front_top_f.max(0.0) as i32;
+    let front_bot = front_bot_f.min((screen_h - 1) as f32) as i32;
+    let top_top = top_top_f.max(0.0) as i32;
+    let top_bot = top_bot_f.min((screen_h - 1) as f32) as i32;
+
+    let body = color;                  // frente
+    let top_col = shade(color, 0.9);   // tapa ligeramente más oscura
+    let edge = 0x000000;               // bordes
+
+    // Relleno por columnas con test de profundidad por-ray
+    for sx in left.max(0)..=right.min(screen_w as i32 - 1) {
+        if dist <= hits[sx as usize].dist_px - 0.5 {
+            // frente
+            for sy in front_top..=front_bot {
+                put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, body);
+            }
+            // tapa (sobre el frente)
+            for sy in top_top..=top_bot {
+                put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, top_col);
+            }
+            drew_any = true;
+        }
+    }
+
+    // Bordes verticales del frente (izq/der), dibujados al final por encima
+    let edge_w = 1;
+    for sx in left.max(0)..=(left + edge_w).min(screen_w as i32 - 1) {
+        if dist <= hits[sx as usize].dist_px - 0.5 {
+            for sy in front_top..=front_bot {
+                put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, edge);
+            }
+            drew_any = true;
+        }
+    }
+    for sx in (right - edge_w).max(0)..=right.min(screen_w as i32 - 1) {
+        if dist <= hits[sx as usize].dist_px - 0.5 {
+            for sy in front_top..=front_bot {
+                put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, edge);
+            }
+            drew_any = true;
+        }
+    }
+
+    // Borde superior de la tapa
+    for sx in left.max(0)..=right.min(screen_w as i32 - 1) {
+        if dist <= hits[sx as usize].dist_px - 0.5 {
+            let y = top_top;
+            if y >= 0 && y < screen_h as i32 {
+                put_pixel(buffer, screen_w, screen_h, sx as usize, y as usize, edge);
+            }
+            drew_any = true;
+        }
+    }
+
+    drew_any
+}
+
+/// Color de pared de un impacto: base por ID (animado), puerta fija y caras horizontales más oscuras.
+fn hit_color(hit: &RayHit, anim_t: f32) -> u32 {
+    // La puerta usa un color fijo para distinguirse
+    let color = if hit.wall_id == DOOR_ID { DOOR_COLOR } else { wall_color_anim(hit.wall_id, anim_t) };
+    // Sombreado simple: caras horizontales un poco más oscuras
+    if hit.hit_vertical { color } else { shade(color, 0.75) }
+}
+
+/// Mezcla lineal por canal: `t = 0` devuelve `a`, `t = 1` devuelve `b`.
+fn lerp_color(a: u32, b: u32, t: f32) -> u32 {
+    let ch = |c: u32, s: u32| ((c >> s) & 0xFF) as f32;
+    let mix = |s: u32| ((ch(a, s) + (ch(b, s) - ch(a, s)) * t).round().clamp(0.0, 255.0) as u32) << s;
+    mix(16) | mix(8) | mix(0)
+}
+
+/// Lanza los rayos muestreados y los expande a una entrada por columna (`hits`, `colors`).
+/// Entre dos muestras se interpola la distancia y el color; `wall_id` y la cara se toman del
+/// rayo muestreado más cercano. Con `ray_step = 1` el resultado es el de un rayo por columna.
+fn expand_rays(map: &Map, player: &Player, screen_w: usize, ray_step: usize, anim_t: f32) -> (Vec<RayHit>, Vec<u32>) {
+    let samples = raycaster::cast_all_rays(map, player, screen_w, ray_step);
+    let mut hits = vec![RayHit::default(); screen_w];
+    let mut colors = vec![0u32; screen_w];
+    for (i, &a) in samples.iter().enumerate() {
+        let c0 = raycaster::sample_column(i, screen_w, ray_step);
+        let ca = hit_color(&a, anim_t);
+        let Some(&b) = samples.get(i + 1) else {
+            hits[c0] = a;
+            colors[c0] = ca;
+            continue;
+        };
+        let c1 = raycaster::sample_column(i + 1, screen_w, ray_step);
+        let cb = hit_color(&b, anim_t);
+        for x in c0..c1 {
+            let t = (x - c0) as f32 / (c1 - c0) as f32;
+            let (mut hit, near_color) = if t < 0.5 { (a, ca) } else { (b, cb) };
+            if a.wall_id != 0 && b.wall_id != 0 {
+                hit.dist_px = a.dist_px + (b.dist_px - a.dist_px) * t;
+                colors[x] = lerp_color(ca, cb, t);
+            } else {
+                colors[x] = near_color;
+            }
+            hits[x] = hit;
+        }
+    }
+    (hits, colors)
+}
+
+/// Lo que hay que pintar en un fotograma de juego.
+pub struct Scene<'a> {
+    pub map: &'a Map,
+    pub player: &'a Player,
+    /// Centro del objetivo en píxeles de mundo.
+    pub objective: (f32, f32),
+    /// Llave si sigue en el mapa.
+    pub key: Option<(f32, f32)>,
+    pub particles: &'a [Particle],
+    pub anim_t: f32,
+    /// Si es mayor que 1, lanza un rayo cada `ray_step` columnas e interpola las intermedias
+    /// (menos rayos a alta resolución a cambio de bordes de pared más suaves/imprecisos).
+    pub ray_step: usize,
 }
 
 /// Dibuja toda la escena en el framebuffer.
-pub fn draw_scene(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Map, player: &Player, obj_x: f32, obj_y: f32, anim_t: f32) {
+pub fn draw_scene(buffer: &mut [u32], screen_w: usize, screen_h: usize, scene: &Scene) {
+    let &Scene { map, player, objective: (obj_x, obj_y), key, particles, anim_t, ray_step } = scene;
     assert_eq!(buffer.len(), screen_w * screen_h, "buffer size mismatch");
 
+    // Proyección: misma focal en ambos ejes (`player.fov` debe venir de `Player::fit_screen`)
+    let (proj_plane, proj_v) = projection_planes(screen_w, screen_h);
+
+    // Horizonte desplazado por la inclinación (pitch) del jugador. Puede quedar fuera de pantalla:
+    // paredes y sprites se centran en él sin recortar, así la vista sigue al pitch en todo el rango.
+    let horizon = screen_h as f32 * 0.5 + player.pitch * proj_v;
+    let half = horizon.clamp(0.0, screen_h as f32) as usize;
+
     // 1) Fondo: cielo (arriba) y suelo (abajo)
-    let half = screen_h / 2;
     for y in 0..half {
         let row = y * screen_w;
         buffer[row..row + screen_w].fill(SKY);
@@ -280,129 +567,47 @@ pub fn draw_scene(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Ma
         buffer[row..row + screen_w].fill(FLOOR);
     }
 
-    // 2) Ray casting para cada columna
-    let hits: Vec<RayHit> = raycaster::cast_all_rays(map, player, screen_w);
-
-    // Proyección: distancia al plano de proyección en píxeles
-    let proj_plane = (screen_w as f32 / 2.0) / (player.fov * 0.5).tan();
+    // 2) Ray casting: un rayo cada `ray_step` columnas; las intermedias interpolan distancia y color
+    let (hits, colors) = expand_rays(map, player, screen_w, ray_step, anim_t);
 
     for x in 0..screen_w {
         let hit = hits[x];
         if !hit.dist_px.is_finite() || hit.wall_id == 0 { continue; }
 
         // Altura de la pared en píxeles: proporcional a TILE_SIZE / dist
-        let mut col_h = (TILE_SIZE as f32 * proj_plane / hit.dist_px).max(1.0);
-        if col_h > screen_h as f32 { col_h = screen_h as f32; }
-
-        let col_h_i = col_h as i32;
-        let center = (screen_h / 2) as i32;
-        let y1 = (center - col_h_i / 2).max(0);
-        let y2 = (center + col_h_i / 2).min(screen_h as i32 - 1);
-
-        // Color base por ID (animado)
-        let mut color = wall_color_anim(hit.wall_id, anim_t);
-        // Sombreado simple: caras horizontales un poco más oscuras
-        if !hit.hit_vertical {
-            color = shade(color, 0.75);
-        }
+        let col_h = wall_column_height(hit.dist_px, screen_w, screen_h).max(1.0);
+
+        // Centrada en el horizonte real; sólo se recorta la parte visible
+        let y1 = (horizon - col_h * 0.5).max(0.0) as i32;
+        let y2 = (horizon + col_h * 0.5).min(screen_h as f32 - 1.0) as i32;
+        if y2 < y1 { continue; }
 
         // Dibuja columna
+        let color = colors[x];
         for yi in y1 as usize..=y2 as usize {
             put_pixel(buffer, screen_w, screen_h, x, yi, color);
         }
     }
 
+    // === LLAVE: cubo dorado más pequeño, sin marcadores de HUD ===
+    if let Some((kx, ky)) = key {
+        if let Some((screen_x, dist, rel)) = sprite_projection(player, screen_w, proj_plane, kx, ky) {
+            if sprite_in_view(player, rel) {
+                let base = (TILE_SIZE as f32) * proj_v / dist * 0.5;
+                draw_cube_sprite(buffer, screen_w, screen_h, &hits, CubeSprite { screen_x, center_y: horizon, dist, base, color: KEY_COLOR });
+            }
+        }
+    }
+
     // === OBJETIVO: Cubo “flotante” con oclusión; marcador HUD si no es visible ===
     {
-        let ox = obj_x;
-        let oy = obj_y;
-        let dx = ox - player.x;
-        let dy = oy - player.y;
-        let dist = (dx * dx + dy * dy).sqrt();
-        if dist.is_finite() && dist > 1.0 {
-            // Ángulo relativo al jugador en [-PI, PI]
-            let mut rel = dy.atan2(dx) - player.angle;
-            while rel > PI { rel -= 2.0 * PI; }
-            while rel < -PI { rel += 2.0 * PI; }
-
-            let mut drew_any = false;
-
+        if let Some((screen_x, dist, rel)) = sprite_projection(player, screen_w, proj_plane, obj_x, obj_y) {
             // Intento de dibujar si cae dentro del FOV (con pequeño margen)
-            if rel.abs() <= player.fov * 0.6 {
-                let screen_center = (screen_w as f32) * 0.5;
-                let screen_x = screen_center + rel.tan() * proj_plane;
-
+            if sprite_in_view(player, rel) {
                 // Tamaño base en píxeles proporcional a TILE_SIZE/dist
-                let base = (TILE_SIZE as f32) * proj_plane / dist;
-                let cube = (base * 0.9).max(6.0);       // ancho del cubo
-                let front_h = (cube * 0.7).max(3.0);    // alto del frente
-                let top_h = (cube * 0.28).max(2.0);     // alto de la tapa
-                let half_w = (cube * 0.5).max(2.0);
-
-                let left = (screen_x - half_w).floor() as i32;
-                let right = (screen_x + half_w).ceil() as i32;
-
-                let center_y = (screen_h as f32) * 0.5;
-                // elevación leve para simular que flota
-                let lift = (cube * 0.18) as f32;
-                let front_top_f = center_y - front_h * 0.5 - lift;
-                let front_bot_f = center_y + front_h * 0.5 - lift;
-                let top_top_f = front_top_f - top_h;
-                let top_bot_f = front_top_f;
-
-                let front_top = front_top_f.max(0.0) as i32;
-                let front_bot = front_bot_f.min((screen_h - 1) as f32) as i32;
-                let top_top = top_top_f.max(0.0) as i32;
-                let top_bot = top_bot_f.min((screen_h - 1) as f32) as i32;
-
-                let body = OBJ_COLOR;                  // frente
-                let top_col = shade(OBJ_COLOR, 0.9);   // tapa ligeramente más oscura
-                let edge = 0x000000;                   // bordes
-
-                // Relleno por columnas con test de profundidad por-ray
-                for sx in left.max(0)..=right.min(screen_w as i32 - 1) {
-                    if dist <= hits[sx as usize].dist_px - 0.5 {
-                        // frente
-                        for sy in front_top..=front_bot {
-                            put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, body);
-                        }
-                        // tapa (sobre el frente)
-                        for sy in top_top..=top_bot {
-                            put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, top_col);
-                        }
-                        drew_any = true;
-                    }
-                }
-
-                // Bordes verticales del frente (izq/der), dibujados al final por encima
-                let edge_w = 1;
-                for sx in left.max(0)..=(left + edge_w).min(screen_w as i32 - 1) {
-                    if dist <= hits[sx as usize].dist_px - 0.5 {
-                        for sy in front_top..=front_bot {
-                            put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, edge);
-                        }
-                        drew_any = true;
-                    }
-                }
-                for sx in (right - edge_w).max(0)..=right.min(screen_w as i32 - 1) {
-                    if dist <= hits[sx as usize].dist_px - 0.5 {
-                        for sy in front_top..=front_bot {
-                            put_pixel(buffer, screen_w, screen_h, sx as usize, sy as usize, edge);
-                        }
-                        drew_any = true;
-                    }
-                }
-
-                // Borde superior de la tapa
-                for sx in left.max(0)..=right.min(screen_w as i32 - 1) {
-                    if dist <= hits[sx as usize].dist_px - 0.5 {
-                        let y = top_top;
-                        if y >= 0 && y < screen_h as i32 {
-                            put_pixel(buffer, screen_w, screen_h, sx as usize, y as usize, edge);
-                        }
-                        drew_any = true;
-                    }
-                }
+                let base = (TILE_SIZE as f32) * proj_v / dist;
+                let cube = CubeSprite { screen_x, center_y: horizon, dist, base, color: OBJ_COLOR };
+                let drew_any = draw_cube_sprite(buffer, screen_w, screen_h, &hits, cube);
 
                 // Si estaba en FOV pero quedó totalmente ocluido por paredes, dibuja un marcador en el borde superior.
                 if !drew_any {
@@ -421,8 +626,6 @@ pub fn draw_scene(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Ma
                 }
             } else {
                 // Fuera de FOV: marcador lateral (izq/der) apuntando hacia la dirección del objetivo
-                let screen_center = (screen_w as f32) * 0.5;
-                let screen_x = screen_center + rel.tan() * proj_plane;
                 let at_left = screen_x < 0.0;
                 let x = if at_left { 0 } else { (screen_w as i32 - 1) };
                 // flecha vertical simple
@@ -434,6 +637,10 @@ pub fn draw_scene(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Ma
             }
         }
     }
+
+    // === PARTÍCULAS: al final, sobre los sprites; test de profundidad sólo contra paredes ===
+    let camera = CameraView { player, horizon, proj_plane, proj_v };
+    draw_particles(buffer, screen_w, screen_h, camera, &hits, particles);
 }
 
 // ====== MENÚ DE BIENVENIDA (un botón: "Jugar") ======
@@ -492,6 +699,7 @@ pub fn draw_menu(buffer: &mut [u32], screen_w: usize, screen_h: usize, selected_
 
     // Hint inferior
     draw_text_centered5x7(buffer, screen_w, screen_h, screen_w/2, py + panel_h - 28, "ENTER O CLIC", 1, TEXT_COLOR);
+    draw_text_centered5x7(buffer, screen_w, screen_h, screen_w/2, py + panel_h - 16, "L NIVEL DE PRUEBA   E EDITOR", 1, TEXT_COLOR);
 }
 
 /// Pantalla de victoria simple
@@ -515,11 +723,225 @@ pub fn draw_victory(buffer: &mut [u32], screen_w: usize, screen_h: usize) {
 
 
 // ====== HUD FPS ======
-pub fn draw_fps_hud(buffer: &mut [u32], screen_w: usize, screen_h: usize, fps: u32) {
+/// `fps` es la tasa medida; `target_fps` el tope configurado (`None` = sin límite).
+pub fn draw_fps_hud(buffer: &mut [u32], screen_w: usize, screen_h: usize, fps: u32, target_fps: Option<u32>) {
     let margin = 8usize;
     let text = format!("FPS {}", fps);
     // Sombra
     draw_text5x7(buffer, screen_w, screen_h, margin + 1, margin + 1, &text, 2, TEXT_SHADOW);
     // Texto
     draw_text5x7(buffer, screen_w, screen_h, margin, margin, &text, 2, TEXT_COLOR);
-}
\ No newline at end of file
+
+    // Tope configurado (debajo, en pequeño)
+    let cap = match target_fps {
+        Some(t) => format!("TOPE {}", t),
+        None => "SIN TOPE".to_string(),
+    };
+    draw_text5x7(buffer, screen_w, screen_h, margin, margin + 18, &cap, 1, TEXT_COLOR);
+}
+
+// ====== HUD LLAVE ======
+pub fn draw_key_hud(buffer: &mut [u32], screen_w: usize, screen_h: usize, has_key: bool) {
+    if !has_key { return; }
+    let margin = 8usize;
+    let text = "LLAVE";
+    let x = screen_w.saturating_sub(margin + text_width5x7(text, 2));
+    draw_text5x7(buffer, screen_w, screen_h, x + 1, margin + 1, text, 2, TEXT_SHADOW);
+    draw_text5x7(buffer, screen_w, screen_h, x, margin, text, 2, KEY_COLOR);
+}
+
+// ====== GRÁFICA DE TIEMPOS DE CUADRO (F3) ======
+const GRAPH_BG: u32 = 0x101018;
+const GRAPH_OK: u32 = 0x00FF88;     // cuadro dentro del objetivo
+const GRAPH_SLOW: u32 = 0xFF4040;   // tirón (por encima del objetivo)
+const GRAPH_TARGET: u32 = 0xFFD700; // línea de referencia del FPS objetivo
+const GRAPH_MAX_MS: f32 = 50.0;     // tope vertical de la gráfica
+
+/// Dibuja en la esquina inferior derecha una barra por cuadro (`frame_times` en segundos, el
+/// más reciente al final) y una línea de referencia en el tiempo del FPS objetivo.
+pub fn draw_frame_graph(buffer: &mut [u32], screen_w: usize, screen_h: usize, frame_times: &[f32], target_fps: Option<u32>) {
+    let margin = 8usize;
+    let bar_w = 2usize;
+    let graph_h = 60usize;
+    let graph_w = frame_times.len().max(1) * bar_w;
+    if graph_w + margin > screen_w || graph_h + margin > screen_h { return; }
+    let x0 = screen_w - margin - graph_w;
+    let y0 = screen_h - margin - graph_h;
+    draw_rect(buffer, screen_w, screen_h, x0, y0, graph_w, graph_h, GRAPH_BG);
+
+    let target_ms = target_fps.map(|fps| 1000.0 / fps as f32);
+    let to_px = |ms: f32| ((ms / GRAPH_MAX_MS).clamp(0.0, 1.0) * graph_h as f32) as usize;
+
+    for (i, &t) in frame_times.iter().enumerate() {
+        let ms = t * 1000.0;
+        let bh = to_px(ms).max(1);
+        // Margen del 10% sobre el objetivo antes de marcarlo como tirón
+        let slow = target_ms.map(|tm| ms > tm * 1.1).unwrap_or(false);
+        let col = if slow { GRAPH_SLOW } else { GRAPH_OK };
+        draw_rect(buffer, screen_w, screen_h, x0 + i * bar_w, y0 + graph_h - bh, bar_w, bh, col);
+    }
+
+    if let Some(tm) = target_ms {
+        let y = (y0 + graph_h - to_px(tm).max(1)) as i32;
+        draw_line(buffer, screen_w, screen_h, x0 as i32, y, (x0 + graph_w - 1) as i32, y, GRAPH_TARGET);
+    }
+}
+
+// ====== EDITOR DE MAPAS ======
+const EDITOR_BG: u32 = 0x0B0B12;
+const EDITOR_FLOOR: u32 = 0x1E1B2E;
+const EDITOR_CURSOR: u32 = 0xFFFFFF;
+const EDITOR_TOP: usize = 40;    // alto de la cabecera
+const EDITOR_BOTTOM: usize = 20; // alto de la línea de ayuda
+
+/// Origen (x, y) y tamaño de celda en píxeles de la rejilla del editor.
+pub fn editor_grid_layout(screen_w: usize, screen_h: usize, map: &Map) -> (usize, usize, usize) {
+    let margin = 8usize;
+    let avail_w = screen_w.saturating_sub(2 * margin);
+    let avail_h = screen_h.saturating_sub(EDITOR_TOP + EDITOR_BOTTOM);
+    let scale = (avail_w / map.width().max(1)).min(avail_h / map.height().max(1)).max(1);
+    let ox = screen_w.saturating_sub(map.width() * scale) / 2;
+    (ox, EDITOR_TOP, scale)
+}
+
+/// Celda bajo el puntero (coordenadas de ventana), si cae dentro de la rejilla.
+pub fn editor_cell_at(screen_w: usize, screen_h: usize, map: &Map, mx: f32, my: f32) -> Option<(i32, i32)> {
+    let (ox, oy, scale) = editor_grid_layout(screen_w, screen_h, map);
+    if mx < ox as f32 || my < oy as f32 { return None; }
+    let cx = ((mx - ox as f32) / scale as f32) as i32;
+    let cy = ((my - oy as f32) / scale as f32) as i32;
+    if map.in_bounds(cx, cy) { Some((cx, cy)) } else { None }
+}
+
+/// Pantalla del editor: el mapa completo con colores por ID, marcadores y la celda bajo el cursor.
+pub fn draw_editor(buffer: &mut [u32], screen_w: usize, screen_h: usize, map: &Map, hover: Option<(i32, i32)>, brush: &str) {
+    draw_rect(buffer, screen_w, screen_h, 0, 0, screen_w, screen_h, EDITOR_BG);
+
+    let (ox, oy, scale) = editor_grid_layout(screen_w, screen_h, map);
+    draw_rect(buffer, screen_w, screen_h, ox, oy, map.width() * scale, map.height() * scale, EDITOR_FLOOR);
+    let view = GridView { ox, oy, scale, cells_x: map.width(), cells_y: map.height() };
+    draw_grid_cells(buffer, screen_w, screen_h, map, view, |_, _, id| wall_color(id));
+
+    // Spawn, objetivo y llave
+    let mut marker = |(cx, cy): (i32, i32), color: u32| {
+        let inset = scale / 4;
+        let x = ox + cx as usize * scale + inset;
+        let y = oy + cy as usize * scale + inset;
+        let size = scale.saturating_sub(2 * inset).max(1);
+        draw_rect(buffer, screen_w, screen_h, x, y, size, size, color);
+    };
+    marker(map.spawn_cell(), MM_PLAYER);
+    marker(map.objective_cell(), MM_OBJECTIVE);
+    if let Some(k) = map.key_cell() { marker(k, MM_KEY); }
+
+    // Contorno de la celda bajo el cursor
+    if let Some((cx, cy)) = hover {
+        let x = (ox + cx as usize * scale) as i32;
+        let y = (oy + cy as usize * scale) as i32;
+        let s = scale as i32 - 1;
+        draw_line(buffer, screen_w, screen_h, x, y, x + s, y, EDITOR_CURSOR);
+        draw_line(buffer, screen_w, screen_h, x, y + s, x + s, y + s, EDITOR_CURSOR);
+        draw_line(buffer, screen_w, screen_h, x, y, x, y + s, EDITOR_CURSOR);
+        draw_line(buffer, screen_w, screen_h, x + s, y, x + s, y + s, EDITOR_CURSOR);
+    }
+
+    let margin = 8usize;
+    draw_text5x7(buffer, screen_w, screen_h, margin, 12, "EDITOR", 2, TEXT_COLOR);
+    let label = format!("PINCEL {}", brush);
+    let lx = screen_w.saturating_sub(margin + text_width5x7(&label, 2));
+    draw_text5x7(buffer, screen_w, screen_h, lx, 12, &label, 2, KEY_COLOR);
+    draw_text_centered5x7(buffer, screen_w, screen_h, screen_w / 2, screen_h.saturating_sub(14),
+        "0 A 5 S O K PINCEL   CLIC PINTAR   F5 GUARDAR   P JUGAR   RETROCESO MENU", 1, TEXT_COLOR);
+}
+
+// ====== CORTINA DE CAMBIO DE MAPA ======
+const WIPE_EDGE: u32 = 0xFF2ED1; // borde neón de la cortina
+const WIPE_EDGE_W: usize = 12;
+
+/// Cortina de izquierda a derecha: con `progress` en [0, 1], a la derecha del borde sigue
+/// visible `prev` (el último cuadro del mapa anterior), que además se oscurece al avanzar.
+pub fn draw_wipe(buffer: &mut [u32], screen_w: usize, screen_h: usize, prev: &[u32], progress: f32) {
+    if prev.len() != buffer.len() { return; }
+    let p = progress.clamp(0.0, 1.0);
+    let edge = (p * screen_w as f32) as usize;
+    if edge >= screen_w { return; }
+    for y in 0..screen_h {
+        let row = y * screen_w;
+        buffer[row + edge..row + screen_w].copy_from_slice(&prev[row + edge..row + screen_w]);
+    }
+    blend_rect(buffer, screen_w, screen_h, (edge, 0, screen_w - edge, screen_h), 0x000000, p * 0.6);
+    blend_rect(buffer, screen_w, screen_h, (edge.saturating_sub(WIPE_EDGE_W / 2), 0, WIPE_EDGE_W, screen_h), WIPE_EDGE, 0.7);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::PlayerConfig;
+
+    #[test]
+    fn ray_step_one_is_one_ray_per_column() {
+        let map = Map::from_ascii(include_str!("../assets/levels/llave.txt")).unwrap();
+        let mut player = Player::from_map_spawn(&map, PlayerConfig::default());
+        player.angle = 0.4;
+        let screen_w = 320;
+        let exact = raycaster::cast_all_rays(&map, &player, screen_w, 1);
+        let (hits, colors) = expand_rays(&map, &player, screen_w, 1, 0.0);
+        assert_eq!((exact.len(), hits.len()), (screen_w, screen_w));
+        for (x, (a, b)) in exact.iter().zip(&hits).enumerate() {
+            assert_eq!((a.dist_px, a.wall_id, a.hit_vertical, a.cell), (b.dist_px, b.wall_id, b.hit_vertical, b.cell), "columna {}", x);
+            assert_eq!(colors[x], hit_color(a, 0.0), "columna {}", x);
+        }
+    }
+
+    #[test]
+    fn wide_aspect_does_not_draw_sprites_behind_the_player() {
+        let map = Map::from_ascii(include_str!("../assets/levels/llave.txt")).unwrap();
+        let mut player = Player::from_map_spawn(&map, PlayerConfig::default());
+        let (w, h) = (2400, 60);
+        player.fit_screen(w, h);
+        assert!(player.fov * 0.6 > FRAC_PI_2);
+        for deg in (91..=104).chain(-104..=-91) {
+            let rel = (deg as f32).to_radians();
+            let dist = TILE_SIZE as f32 * 2.0;
+            let dir = player.angle + rel;
+            let objective = (player.x + dir.cos() * dist, player.y + dir.sin() * dist);
+            let scene = Scene { map: &map, player: &player, objective, key: None, particles: &[], anim_t: 0.0, ray_step: 1 };
+            let mut buffer = vec![0u32; w * h];
+            draw_scene(&mut buffer, w, h, &scene);
+            // Fuera de las columnas del marcador lateral no debe haber nada del objetivo
+            let drawn = buffer.chunks(w).flat_map(|row| &row[2..w - 2]).filter(|&&c| c == OBJ_COLOR).count();
+            assert_eq!(drawn, 0, "objetivo a {}°", deg);
+        }
+    }
+
+    #[test]
+    fn view_keeps_moving_after_horizon_leaves_screen() {
+        let map = Map::from_ascii(include_str!("../assets/levels/llave.txt")).unwrap();
+        let mut player = Player::from_map_spawn(&map, PlayerConfig::default());
+        let (w, h) = (160, 120);
+        let mut frame = |pitch: f32| {
+            player.pitch = pitch;
+            let mut buffer = vec![0u32; w * h];
+            let scene = Scene { map: &map, player: &player, objective: (0.0, 0.0), key: None, particles: &[], anim_t: 0.0, ray_step: 1 };
+            draw_scene(&mut buffer, w, h, &scene);
+            buffer
+        };
+        // Ambos con el horizonte ya por encima de la pantalla
+        assert!(0.5 * projection_planes(w, h).1 > h as f32 * 0.5);
+        assert_ne!(frame(0.5), frame(crate::player::PITCH_MAX));
+    }
+
+    #[test]
+    fn wall_height_depends_on_height_not_width() {
+        let dist = 3.0 * TILE_SIZE as f32;
+        let h_4_3 = wall_column_height(dist, 800, 600);
+        let h_wide = wall_column_height(dist, 1400, 600);
+        assert!((h_4_3 - h_wide).abs() < 1e-3, "{} vs {}", h_4_3, h_wide);
+
+        // Misma escala en ambos ejes a 4:3 y a 16:9: paredes y cubos sin estirar
+        for (w, h) in [(800, 600), (1920, 1080)] {
+            let (horiz, vert) = projection_planes(w, h);
+            assert!((horiz - vert).abs() < 1e-2, "{}x{}: {} vs {}", w, h, horiz, vert);
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use crate::GameMode;
+use crate::player::PlayerConfig;
+
+/// Archivo de ajustes (formato `clave = valor`, `#` para comentarios).
+pub const SETTINGS_PATH: &str = "settings.cfg";
+
+/// Topes de FPS que se recorren con F2 (`None` = sin límite).
+pub const FPS_OPTIONS: [Option<u32>; 4] = [Some(30), Some(60), Some(120), None];
+
+/// Multiplicadores por dificultad sobre las velocidades base del jugador.
+#[derive(Clone, Copy)]
+pub struct DifficultyParams {
+    pub move_speed_mul: f32,
+    pub rot_speed_mul: f32,
+}
+
+impl Default for DifficultyParams {
+    fn default() -> Self {
+        Self { move_speed_mul: 1.0, rot_speed_mul: 1.0 }
+    }
+}
+
+/// Ajustes de juego. Si falta el archivo o una clave es inválida, se usan los valores por defecto.
+pub struct Settings {
+    /// FPS objetivo para `limit_update_rate` (`None` = sin límite).
+    pub target_fps: Option<u32>,
+    /// Resolución usada en pantalla completa (minifb no permite consultar la del monitor).
+    pub display_size: (usize, usize),
+    /// Un rayo cada `ray_step` columnas (1 = uno por columna); el resto se interpola.
+    pub ray_step: usize,
+    /// Sensibilidad del arrastre con el ratón (rad/píxel en X, inclinación/píxel en Y).
+    pub mouse_sens_x: f32,
+    pub mouse_sens_y: f32,
+    /// Invierte el eje vertical del ratón.
+    pub invert_y: bool,
+    /// Velocidades base del jugador (px/seg, rad/seg).
+    pub player: PlayerConfig,
+    pub normal: DifficultyParams,
+    pub dificil: DifficultyParams,
+    /// Cortina animada al rotar de mapa (false = cambio instantáneo).
+    pub map_transition: bool,
+    /// Música por semilla (`bgm.<seed> = ruta`); las demás usan la pista por defecto.
+    pub bgm_tracks: HashMap<u32, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            target_fps: Some(60),
+            display_size: (1920, 1080),
+            ray_step: 1,
+            mouse_sens_x: 0.004,
+            mouse_sens_y: 0.004,
+            invert_y: false,
+            player: PlayerConfig::default(),
+            normal: DifficultyParams::default(),
+            dificil: DifficultyParams::default(),
+            map_transition: true,
+            bgm_tracks: HashMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Lee los ajustes desde `path`; las líneas inválidas se reportan y se ignoran.
+    pub fn load(path: &str) -> Self {
+        let mut settings = Self::default();
+        let Ok(text) = fs::read_to_string(path) else { return settings; };
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("{}:{}: se esperaba `clave = valor`", path, n + 1);
+                continue;
+            };
+            if let Err(e) = settings.apply(key.trim(), value.trim()) {
+                eprintln!("{}:{}: {}", path, n + 1, e);
+            }
+        }
+        settings
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "target_fps" => self.target_fps = parse_fps(value)?,
+            "display_size" => self.display_size = parse_size(value)?,
+            "ray_step" => {
+                self.ray_step = match value.parse::<usize>() {
+                    Ok(n) if (1..=8).contains(&n) => n,
+                    _ => return Err(format!("ray_step inválido `{}` (1..=8)", value)),
+                }
+            }
+            "mouse_sens_x" => self.mouse_sens_x = parse_sensitivity(key, value)?,
+            "mouse_sens_y" => self.mouse_sens_y = parse_sensitivity(key, value)?,
+            "invert_y" => self.invert_y = parse_bool(key, value)?,
+            "move_speed" => self.player.move_speed = parse_positive(key, value)?,
+            "rot_speed" => self.player.rot_speed = parse_positive(key, value)?,
+            "normal.move_speed_mul" => self.normal.move_speed_mul = parse_positive(key, value)?,
+            "normal.rot_speed_mul" => self.normal.rot_speed_mul = parse_positive(key, value)?,
+            "dificil.move_speed_mul" => self.dificil.move_speed_mul = parse_positive(key, value)?,
+            "dificil.rot_speed_mul" => self.dificil.rot_speed_mul = parse_positive(key, value)?,
+            "map_transition" => self.map_transition = parse_bool(key, value)?,
+            _ if key.starts_with("bgm.") => {
+                let seed = key["bgm.".len()..].parse::<u32>()
+                    .map_err(|_| format!("semilla inválida en `{}` (se espera bgm.<n>)", key))?;
+                if value.is_empty() { return Err(format!("{} sin ruta", key)); }
+                self.bgm_tracks.insert(seed, value.to_string());
+            }
+            _ => return Err(format!("clave desconocida `{}`", key)),
+        }
+        Ok(())
+    }
+
+    /// Duración mínima de cuadro para `Window::limit_update_rate`.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        self.target_fps.map(|fps| Duration::from_micros(1_000_000 / fps as u64))
+    }
+
+    /// Velocidades del jugador para `mode` (base × multiplicadores, acotadas en `Player`).
+    pub fn player_config(&self, mode: GameMode) -> PlayerConfig {
+        let params = match mode {
+            GameMode::Normal => self.normal,
+            GameMode::Dificil => self.dificil,
+        };
+        PlayerConfig {
+            move_speed: self.player.move_speed * params.move_speed_mul,
+            rot_speed: self.player.rot_speed * params.rot_speed_mul,
+        }
+    }
+
+    /// Pista para la semilla `seed`: la configurada si existe en disco, si no `default`.
+    pub fn bgm_path<'a>(&'a self, seed: u32, default: &'a str) -> &'a str {
+        match self.bgm_tracks.get(&seed) {
+            Some(path) if Path::new(path).exists() => path,
+            _ => default,
+        }
+    }
+
+    /// Pasa al siguiente tope de `FPS_OPTIONS`.
+    pub fn cycle_target_fps(&mut self) {
+        let idx = FPS_OPTIONS.iter().position(|&o| o == self.target_fps).unwrap_or(0);
+        self.target_fps = FPS_OPTIONS[(idx + 1) % FPS_OPTIONS.len()];
+    }
+}
+
+/// `30`, `60`, `120`... o `0`/`sin_limite` para desactivar el tope.
+fn parse_fps(value: &str) -> Result<Option<u32>, String> {
+    match value {
+        "sin_limite" | "uncapped" => Ok(None),
+        // Cualquier forma de cero (`0`, `00`, `+0`) es sin límite: nunca `Some(0)`
+        _ => match value.parse::<u32>() {
+            Ok(0) => Ok(None),
+            Ok(fps) if fps <= 1000 => Ok(Some(fps)),
+            _ => Err(format!("target_fps inválido `{}`", value)),
+        },
+    }
+}
+
+/// `ANCHOxALTO`, p. ej. `1920x1080`.
+fn parse_size(value: &str) -> Result<(usize, usize), String> {
+    let parsed = value.split_once('x').and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+    match parsed {
+        Some((w, h)) if w >= 320 && h >= 240 => Ok((w, h)),
+        _ => Err(format!("display_size inválido `{}` (se espera ANCHOxALTO)", value)),
+    }
+}
+
+fn parse_sensitivity(key: &str, value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(v) if v > 0.0 && v <= 0.1 => Ok(v),
+        _ => Err(format!("{} inválido `{}` (0 < s <= 0.1)", key, value)),
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "si" | "sí" | "1" => Ok(true),
+        "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("{} inválido `{}` (true/false)", key, value)),
+    }
+}
+
+fn parse_positive(key: &str, value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+        _ => Err(format!("{} inválido `{}` (debe ser > 0)", key, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_fps_in_any_form_is_uncapped() {
+        let mut settings = Settings::default();
+        for zero in ["0", "00", "+0", "sin_limite"] {
+            settings.apply("target_fps", zero).unwrap();
+            assert_eq!(settings.target_fps, None, "{}", zero);
+            assert_eq!(settings.frame_duration(), None);
+        }
+        settings.apply("target_fps", "144").unwrap();
+        assert_eq!(settings.target_fps, Some(144));
+        assert!(settings.apply("target_fps", "1001").is_err());
+    }
+}